@@ -6,6 +6,12 @@ use cbindgen::Config;
 
 
 fn main() {
+    // The header only describes the C ABI, which does not exist without the
+    // `ffi` feature
+    if env::var_os("CARGO_FEATURE_FFI").is_none() {
+        return;
+    }
+
     let crate_dir = env::var("CARGO_MANIFEST_DIR").unwrap();
 
     let package_name = env::var("CARGO_PKG_NAME").unwrap();
@@ -14,14 +20,29 @@ fn main() {
         .display()
         .to_string();
 
-    let config = Config {
+    let mut config = Config {
         namespace: Some(String::from("ffi")),
         ..Default::default()
     };
+    // These enums are passed to functions as plain integers, so cbindgen
+    // would not find them on its own
+    config.export.include = vec![
+        String::from("SfDungeonType"),
+        String::from("SfFortressResource"),
+        String::from("SfUnderworldResource"),
+    ];
 
     cbindgen::generate_with_config(&crate_dir, config)
       .unwrap()
       .write_to_file(&output_file);
+
+    // The C++ wrappers include the generated header, so they have to live in
+    // the same directory
+    std::fs::copy(
+        PathBuf::from(&crate_dir).join("include").join("sf.hpp"),
+        target_dir().join("sf.hpp"),
+    )
+    .unwrap();
 }
 
 /// Find the location of the `target/` directory. Note that this may be 
@@ -33,4 +54,4 @@ fn target_dir() -> PathBuf {
     } else {
         PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap()).join("target")
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,562 @@
+// C++17 wrappers around the C ABI of sf-api. This header is shipped as is and
+// copied next to the cbindgen generated `sf-api.hpp` by `build.rs`, whenever
+// the `ffi` feature is enabled.
+//
+// All classes own their handle and release it with the matching `sf_*_free`
+// function, when they go out of scope. They can be moved, but not copied.
+// Failures are reported by throwing `sf::Error`, which carries the structured
+// error code and message of the failed call. Strings, that are returned as
+// `std::string_view`, borrow from the object they were read from and are only
+// valid as long as that object is alive and has not been modified.
+#pragma once
+
+#include <chrono>
+#include <cstddef>
+#include <cstdint>
+#include <exception>
+#include <functional>
+#include <memory>
+#include <optional>
+#include <stdexcept>
+#include <string>
+#include <string_view>
+#include <utility>
+
+#include "sf-api.hpp"
+
+namespace sf {
+
+using ErrorCode = ffi::SfErrorCode;
+using ConnectionErrorKind = ffi::SfConnectionErrorKind;
+using Attributes = ffi::SfAttributes;
+using Quest = ffi::SfQuest;
+using ActionKind = ffi::SfActionKind;
+using CurrentAction = ffi::SfCurrentAction;
+using Arena = ffi::SfArena;
+using DungeonType = ffi::SfDungeonType;
+using DungeonState = ffi::SfDungeonState;
+using DungeonProgress = ffi::SfDungeonProgress;
+using Resource = ffi::SfResource;
+using FortressResource = ffi::SfFortressResource;
+using UnderworldResource = ffi::SfUnderworldResource;
+using SimulationResult = ffi::SfSimulationResult;
+using LogLevel = ffi::SfLogLevel;
+using TimePoint = std::chrono::system_clock::time_point;
+
+/// Thrown, whenever one of the underlying `sf_*` functions fails
+class Error : public std::runtime_error {
+  public:
+    Error(ErrorCode code, const std::string &message,
+          ConnectionErrorKind connection_kind = ConnectionErrorKind::None,
+          std::uint16_t http_status = 0)
+        : std::runtime_error(message), code_(code),
+          connection_kind_(connection_kind), http_status_(http_status) {}
+
+    /// The kind of error, that occurred
+    ErrorCode code() const noexcept { return code_; }
+
+    /// What part of the communication failed, if this is a connection error
+    ConnectionErrorKind connection_kind() const noexcept {
+        return connection_kind_;
+    }
+
+    /// The HTTP status code of the response, that caused this error, or 0
+    std::uint16_t http_status() const noexcept { return http_status_; }
+
+  private:
+    ErrorCode code_;
+    ConnectionErrorKind connection_kind_;
+    std::uint16_t http_status_;
+};
+
+namespace detail {
+
+/// Builds an `Error` from the last error of this thread and resets it
+inline Error last_error() {
+    const ErrorCode code = ffi::sf_last_error_code();
+    const char *message = ffi::sf_last_error_message();
+    Error error(code, message ? message : "unknown error",
+                ffi::sf_last_error_connection_kind(),
+                ffi::sf_last_error_http_status());
+    ffi::sf_last_error_clear();
+    return error;
+}
+
+template <typename T> T *check(T *ptr) {
+    if (!ptr) {
+        throw last_error();
+    }
+    return ptr;
+}
+
+inline void check(bool ok) {
+    if (!ok) {
+        throw last_error();
+    }
+}
+
+inline std::optional<std::string_view> view(ffi::SfStr str) {
+    if (!str.ptr) {
+        return std::nullopt;
+    }
+    return std::string_view(str.ptr, str.len);
+}
+
+inline std::optional<TimePoint> time_point(int64_t ms) {
+    if (ms == 0) {
+        return std::nullopt;
+    }
+    return TimePoint(std::chrono::milliseconds(ms));
+}
+
+template <typename T, void (*Free)(T *)> struct Deleter {
+    void operator()(T *ptr) const noexcept { Free(ptr); }
+};
+
+template <typename T, void (*Free)(T *)>
+using Handle = std::unique_ptr<T, Deleter<T, Free>>;
+
+/// Takes ownership of a string returned by the library
+inline std::string take_string(char *str) {
+    Handle<char, ffi::sf_string_free> owned(check(str));
+    return std::string(owned.get());
+}
+
+} // namespace detail
+
+/// Called for every log message of the library. This can be called from any
+/// thread, so it has to be thread safe. Exceptions thrown by it are swallowed,
+/// because they must not unwind into the library
+using LogCallback = std::function<void(
+    LogLevel level, std::string_view target, std::string_view message)>;
+
+namespace detail {
+
+inline void log_trampoline(LogLevel level, ffi::SfStr target,
+                           ffi::SfStr message, void *user_data) noexcept {
+    try {
+        (*static_cast<LogCallback *>(user_data))(
+            level, view(target).value_or(""), view(message).value_or(""));
+    } catch (...) {
+    }
+}
+
+} // namespace detail
+
+/// Sends all log messages of the library up to `level` to the callback. An
+/// empty callback disables logging again
+inline void set_log_callback(LogLevel level, LogCallback callback) {
+    static std::unique_ptr<LogCallback> current;
+    auto next = callback ? std::make_unique<LogCallback>(std::move(callback))
+                         : nullptr;
+    detail::check(ffi::sf_set_log_callback(
+        level, next ? detail::log_trampoline : nullptr, next.get()));
+    // The library guarantees, that the previous callback is not in use
+    // anymore at this point
+    current = std::move(next);
+}
+
+/// The runtime, that drives all network communication. Create this once and
+/// share it between all sessions. It has to outlive every request, that was
+/// started on it
+class Runtime {
+  public:
+    /// Creates a runtime with `worker_threads` threads. 0 uses one thread per
+    /// CPU core
+    explicit Runtime(std::size_t worker_threads = 0)
+        : handle_(detail::check(ffi::sf_runtime_new(worker_threads))) {}
+
+    ffi::SfRuntime *get() const noexcept { return handle_.get(); }
+
+  private:
+    detail::Handle<ffi::SfRuntime, ffi::sf_runtime_free> handle_;
+};
+
+/// A response, that the server sent for a command
+class Response {
+  public:
+    /// Takes ownership of a raw response handle
+    explicit Response(ffi::Response *raw) : handle_(detail::check(raw)) {}
+
+    /// Parses a raw response body, as the server would send it
+    static Response parse(const std::string &body,
+                          std::optional<TimePoint> received_at = {}) {
+        int64_t ms = 0;
+        if (received_at) {
+            ms = std::chrono::duration_cast<std::chrono::milliseconds>(
+                     received_at->time_since_epoch())
+                     .count();
+        }
+        return Response(ffi::sf_response_parse(body.c_str(), ms));
+    }
+
+    std::size_t key_count() const noexcept {
+        return ffi::sf_response_key_count(get());
+    }
+
+    /// The key at the given index, or nothing, if it is out of bounds
+    std::optional<std::string_view> key(std::size_t idx) const noexcept {
+        return detail::view(ffi::sf_response_key(get(), idx));
+    }
+
+    /// The raw value the server sent for this key, or nothing, if the response
+    /// does not contain it
+    std::optional<std::string_view> value(const std::string &key) const {
+        return detail::view(ffi::sf_response_value(get(), key.c_str()));
+    }
+
+    /// The raw response body
+    std::string_view raw() const noexcept {
+        return detail::view(ffi::sf_response_raw(get())).value_or("");
+    }
+
+    /// The time at which the response was received
+    TimePoint received_at() const noexcept {
+        return TimePoint(
+            std::chrono::milliseconds(ffi::sf_response_received_at(get())));
+    }
+
+    ffi::Response *get() const noexcept { return handle_.get(); }
+
+  private:
+    detail::Handle<ffi::Response, ffi::sf_response_free> handle_;
+};
+
+/// A request, that has been encoded and encrypted by `Session::prepare`, but
+/// not yet sent
+class Request {
+  public:
+    /// Takes ownership of a raw request handle
+    explicit Request(ffi::PreparedRequest *raw) : handle_(detail::check(raw)) {}
+
+    /// The full url, that the request has to be sent to via GET
+    std::string_view url() const noexcept {
+        return detail::view(ffi::sf_request_url(get())).value_or("");
+    }
+
+    std::size_t header_count() const noexcept {
+        return ffi::sf_request_header_count(get());
+    }
+
+    /// The (name, value) pair of the header at the given index
+    std::pair<std::string_view, std::string_view>
+    header(std::size_t idx) const noexcept {
+        return {
+            detail::view(ffi::sf_request_header_name(get(), idx)).value_or(""),
+            detail::view(ffi::sf_request_header_value(get(), idx))
+                .value_or("")};
+    }
+
+    /// The number of the request within its session
+    uint32_t sequence() const noexcept {
+        return ffi::sf_request_sequence(get());
+    }
+
+    ffi::PreparedRequest *get() const noexcept { return handle_.get(); }
+
+  private:
+    detail::Handle<ffi::PreparedRequest, ffi::sf_request_free> handle_;
+};
+
+/// Called, once an async request has finished. On failure `error` is set and
+/// `response` is empty. This is called from one of the runtime threads.
+/// Exceptions thrown by it are swallowed, because they must not unwind into the
+/// library
+using ResponseCallback =
+    std::function<void(std::optional<Response> response,
+                       std::exception_ptr error)>;
+
+namespace detail {
+
+inline void response_trampoline(void *user_data, ffi::Response *response,
+                                ffi::SfErrorCode error) noexcept {
+    std::unique_ptr<ResponseCallback> callback(
+        static_cast<ResponseCallback *>(user_data));
+    try {
+        if (error != ErrorCode::Ok || !response) {
+            (*callback)(std::nullopt, std::make_exception_ptr(last_error()));
+            return;
+        }
+        (*callback)(Response(response), nullptr);
+    } catch (...) {
+    }
+}
+
+/// Hands the callback to `start`, which has to start the request. Throws, if
+/// the request could not be started
+template <typename Start>
+void start_async(ResponseCallback callback, Start start) {
+    auto owned = std::make_unique<ResponseCallback>(std::move(callback));
+    check(start(owned.get()));
+    // The trampoline is now responsible for freeing the callback
+    owned.release();
+}
+
+} // namespace detail
+
+/// A session, that can talk to the server on behalf of one character
+class Session {
+  public:
+    Session(const std::string &username, const std::string &password,
+            const std::string &server_url)
+        : handle_(detail::check(ffi::sf_session_new(
+              username.c_str(), password.c_str(), server_url.c_str()))) {}
+
+    /// Takes ownership of a raw session handle
+    explicit Session(ffi::Session *raw) : handle_(detail::check(raw)) {}
+
+    /// The name of the character, that this session is responsible for
+    std::string_view username() const noexcept {
+        return detail::view(ffi::sf_session_username(get())).value_or("");
+    }
+
+    /// The URL of the server, that this session is sending requests to
+    std::string_view server_url() const noexcept {
+        return detail::view(ffi::sf_session_server_url(get())).value_or("");
+    }
+
+    /// Logs in and blocks until the server has responded. This must not be
+    /// called from a runtime thread (a callback)
+    void login(const Runtime &runtime) {
+        detail::check(ffi::sf_session_login(runtime.get(), get()));
+    }
+
+    /// Logs in without blocking. The session must not be used, or destroyed
+    /// until the callback has been called
+    void login_async(const Runtime &runtime, ResponseCallback callback) {
+        detail::start_async(std::move(callback), [&](ResponseCallback *cb) {
+            return ffi::sf_session_login_async(
+                runtime.get(), get(), detail::response_trampoline, cb);
+        });
+    }
+
+    /// Sends a command, serialized as JSON, and blocks until the server has
+    /// responded. This must not be called from a runtime thread (a callback)
+    Response send_command(const Runtime &runtime,
+                          const std::string &command_json) {
+        return Response(ffi::sf_session_send_command(runtime.get(), get(),
+                                                     command_json.c_str()));
+    }
+
+    /// Encodes and encrypts the command, without sending it. Send the request
+    /// with your own HTTP client and hand the body of the response to
+    /// `accept`
+    Request prepare(const std::string &command_json) const {
+        return Request(ffi::sf_session_prepare(get(), command_json.c_str()));
+    }
+
+    /// Parses the body, that the server sent for a prepared request and
+    /// updates the session with it
+    Response accept(const std::string &body,
+                    std::optional<TimePoint> received_at = {}) {
+        int64_t ms = 0;
+        if (received_at) {
+            ms = std::chrono::duration_cast<std::chrono::milliseconds>(
+                     received_at->time_since_epoch())
+                     .count();
+        }
+        return Response(ffi::sf_session_accept(get(), body.c_str(), ms));
+    }
+
+    /// Sends a command without blocking. The session must not be used, or
+    /// destroyed until the callback has been called
+    void send_command_async(const Runtime &runtime,
+                            const std::string &command_json,
+                            ResponseCallback callback) {
+        detail::start_async(std::move(callback), [&](ResponseCallback *cb) {
+            return ffi::sf_session_send_command_async(
+                runtime.get(), get(), command_json.c_str(),
+                detail::response_trampoline, cb);
+        });
+    }
+
+    /// Retrieves new SSO credentials from the account of this session. Only
+    /// available, if the library was built with the `sso` feature
+    void renew_sso_creds(const Runtime &runtime) {
+        detail::check(ffi::sf_session_renew_sso_creds(runtime.get(), get()));
+    }
+
+    ffi::Session *get() const noexcept { return handle_.get(); }
+
+  private:
+    detail::Handle<ffi::Session, ffi::sf_session_free> handle_;
+};
+
+/// The sessions of all characters on a SSO account
+class SessionList {
+  public:
+    /// Takes ownership of a raw session list handle
+    explicit SessionList(ffi::SfSessionList *raw)
+        : handle_(detail::check(raw)) {}
+
+    std::size_t size() const noexcept {
+        return ffi::sf_session_list_len(get());
+    }
+
+    /// Removes the session at the given index from the list. Throws, if the
+    /// session was already taken, or its server could not be determined
+    Session take(std::size_t idx) {
+        return Session(ffi::sf_session_list_take(get(), idx));
+    }
+
+    ffi::SfSessionList *get() const noexcept { return handle_.get(); }
+
+  private:
+    detail::Handle<ffi::SfSessionList, ffi::sf_session_list_free> handle_;
+};
+
+/// A S&F SSO account. Only available, if the library was built with the `sso`
+/// feature
+class Account {
+  public:
+    /// Takes ownership of a raw account handle
+    explicit Account(ffi::SFAccount *raw) : handle_(detail::check(raw)) {}
+
+    /// Logs in with the clear text credentials and blocks until the server
+    /// has responded
+    static Account login(const Runtime &runtime, const std::string &username,
+                         const std::string &password) {
+        return Account(ffi::sf_account_login(runtime.get(), username.c_str(),
+                                             password.c_str()));
+    }
+
+    /// Logs in with the hash of the password and blocks until the server has
+    /// responded
+    static Account login_hashed(const Runtime &runtime,
+                                const std::string &username,
+                                const std::string &pw_hash) {
+        return Account(ffi::sf_account_login_hashed(
+            runtime.get(), username.c_str(), pw_hash.c_str()));
+    }
+
+    std::string_view username() const noexcept {
+        return detail::view(ffi::sf_account_username(get())).value_or("");
+    }
+
+    /// Fetches the sessions of all characters on this account. This consumes
+    /// the account, even if the request fails
+    SessionList characters(const Runtime &runtime) && {
+        return SessionList(
+            ffi::sf_account_characters(runtime.get(), handle_.release()));
+    }
+
+    ffi::SFAccount *get() const noexcept { return handle_.get(); }
+
+  private:
+    detail::Handle<ffi::SFAccount, ffi::sf_account_free> handle_;
+};
+
+/// The full state of a character, built from the login response and kept up
+/// to date with the responses of all later commands
+class GameState {
+  public:
+    /// Creates the gamestate from the login response of a session
+    explicit GameState(const Response &login_response)
+        : handle_(detail::check(ffi::sf_gamestate_new(login_response.get()))) {}
+
+    /// Applies the response of any command to this gamestate
+    void update(const Response &response) {
+        detail::check(ffi::sf_gamestate_update(get(), response.get()));
+    }
+
+    /// Serializes the full gamestate as JSON
+    std::string to_json() const {
+        return detail::take_string(ffi::sf_gamestate_to_json(get()));
+    }
+
+    std::string_view name() const noexcept {
+        return detail::view(ffi::sf_character_name(get())).value_or("");
+    }
+
+    uint16_t level() const noexcept { return ffi::sf_character_level(get()); }
+
+    uint64_t silver() const noexcept {
+        return ffi::sf_character_silver(get());
+    }
+
+    uint32_t mushrooms() const noexcept {
+        return ffi::sf_character_mushrooms(get());
+    }
+
+    Attributes attribute_basis() const noexcept {
+        return ffi::sf_character_attribute_basis(get());
+    }
+
+    Attributes attribute_additions() const noexcept {
+        return ffi::sf_character_attribute_additions(get());
+    }
+
+    uint32_t thirst() const noexcept { return ffi::sf_tavern_thirst(get()); }
+
+    /// One of the three quests in the tavern, or nothing, if `idx` is out of
+    /// bounds
+    std::optional<Quest> quest(std::size_t idx) const noexcept {
+        Quest quest{};
+        if (!ffi::sf_tavern_quest(get(), idx, &quest)) {
+            ffi::sf_last_error_clear();
+            return std::nullopt;
+        }
+        return quest;
+    }
+
+    CurrentAction current_action() const noexcept {
+        return ffi::sf_tavern_current_action(get());
+    }
+
+    /// The time at which the current action is finished, if there is one
+    std::optional<TimePoint> busy_until() const noexcept {
+        return detail::time_point(current_action().busy_until);
+    }
+
+    Arena arena() const noexcept { return ffi::sf_arena(get()); }
+
+    DungeonProgress dungeon_progress(DungeonType type,
+                                     uint32_t id) const noexcept {
+        return ffi::sf_dungeon_progress(
+            get(), static_cast<uint32_t>(type), id);
+    }
+
+    /// The resource in the fortress, or nothing, if the character has not
+    /// unlocked the fortress yet
+    std::optional<Resource>
+    fortress_resource(FortressResource type) const noexcept {
+        Resource res{};
+        if (!ffi::sf_fortress_resource(
+                get(), static_cast<uint32_t>(type), &res)) {
+            ffi::sf_last_error_clear();
+            return std::nullopt;
+        }
+        return res;
+    }
+
+    /// The resource in the underworld, or nothing, if the character has not
+    /// unlocked the underworld yet
+    std::optional<Resource>
+    underworld_resource(UnderworldResource type) const noexcept {
+        Resource res{};
+        if (!ffi::sf_underworld_resource(
+                get(), static_cast<uint32_t>(type), &res)) {
+            ffi::sf_last_error_clear();
+            return std::nullopt;
+        }
+        return res;
+    }
+
+    /// Simulates `iterations` fights against the next enemy of the dungeon.
+    /// Throws, if the dungeon has no enemy to fight
+    SimulationResult simulate_dungeon(DungeonType type, uint32_t id,
+                                      uint32_t iterations) const {
+        SimulationResult res{};
+        detail::check(
+            ffi::sf_simulate_dungeon(get(), static_cast<uint32_t>(type), id,
+                                 iterations, &res));
+        return res;
+    }
+
+    ffi::GameState *get() const noexcept { return handle_.get(); }
+
+  private:
+    detail::Handle<ffi::GameState, ffi::sf_gamestate_free> handle_;
+};
+
+} // namespace sf
@@ -0,0 +1,50 @@
+//! Runs a local mock S&F server. Usage:
+//!
+//! `sf-mock-server [--addr <ip:port>] [--fixtures <dir>] [--account
+//! <name:password>]..`
+//!
+//! Without any `--account`, every login is accepted. The fixture directory
+//! contains `<command name>.txt` files with the response to each command
+use sf_api::mock_server::{MockServer, MockServerConfig};
+
+#[tokio::main]
+async fn main() {
+    let mut addr = "127.0.0.1:8080".to_string();
+    let mut config = MockServerConfig::default();
+
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let Some(value) = args.next() else {
+            exit(&format!("Missing value for {arg}"));
+        };
+        match arg.as_str() {
+            "--addr" => addr = value,
+            "--fixtures" => {
+                if let Err(e) = config.load_fixtures(&value) {
+                    exit(&format!("Could not load fixtures from {value}: {e}"));
+                }
+            }
+            "--account" => {
+                let Some((name, password)) = value.split_once(':') else {
+                    exit("Accounts have to be given as name:password");
+                };
+                config = config.with_account(name, password);
+            }
+            _ => exit(&format!("Unknown argument: {arg}")),
+        }
+    }
+
+    let listener = match tokio::net::TcpListener::bind(&addr).await {
+        Ok(listener) => listener,
+        Err(e) => exit(&format!("Could not bind to {addr}: {e}")),
+    };
+    println!("Mock server listening on http://{addr}/");
+    if let Err(e) = MockServer::new(config).serve(listener).await {
+        exit(&format!("Mock server stopped: {e}"));
+    }
+}
+
+fn exit(msg: &str) -> ! {
+    eprintln!("{msg}");
+    std::process::exit(1)
+}
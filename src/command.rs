@@ -839,20 +839,180 @@ pub enum TimeSkip {
     Glass = 2,
 }
 
+#[derive(Clone, Copy)]
+/// Shows a command just like its `Debug` implementation, but hides passwords,
+/// password hashes and tokens, so that it can safely be logged. Created with
+/// `Command::redacted()`
+pub struct RedactedCommand<'a>(&'a Command);
+
+impl std::fmt::Debug for RedactedCommand<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hidden = format_args!("<redacted>");
+        match self.0 {
+            Command::Login {
+                username,
+                login_count,
+                ..
+            } => f
+                .debug_struct("Login")
+                .field("username", username)
+                .field("pw_hash", &hidden)
+                .field("login_count", login_count)
+                .finish(),
+            #[cfg(feature = "sso")]
+            Command::SSOLogin {
+                uuid, character_id, ..
+            } => f
+                .debug_struct("SSOLogin")
+                .field("uuid", uuid)
+                .field("character_id", character_id)
+                .field("bearer_token", &hidden)
+                .finish(),
+            Command::Register {
+                username,
+                gender,
+                race,
+                class,
+                ..
+            } => f
+                .debug_struct("Register")
+                .field("username", username)
+                .field("password", &hidden)
+                .field("gender", gender)
+                .field("race", race)
+                .field("class", class)
+                .finish(),
+            Command::ChangePassword { username, .. } => f
+                .debug_struct("ChangePassword")
+                .field("username", username)
+                .field("old", &hidden)
+                .field("new", &hidden)
+                .finish(),
+            Command::ChangeMailAddress {
+                old_mail,
+                new_mail,
+                username,
+                ..
+            } => f
+                .debug_struct("ChangeMailAddress")
+                .field("old_mail", old_mail)
+                .field("new_mail", new_mail)
+                .field("password", &hidden)
+                .field("username", username)
+                .finish(),
+            command => command.fmt(f),
+        }
+    }
+}
+
 impl Command {
+    /// Gives you a view of this command, whose `Debug` output does not
+    /// contain any credentials. Use this, whenever a command is logged
+    #[must_use]
+    pub fn redacted(&self) -> RedactedCommand<'_> {
+        RedactedCommand(self)
+    }
+
+    /// Checks if sending this command multiple times has the same effect, as
+    /// sending it once. This is true for commands, that only look at data,
+    /// like `Update`, or `ViewPlayer`. Only these commands are safe to send
+    /// again, if it is unclear, whether or not the server has received them
+    #[must_use]
+    #[allow(deprecated)]
+    pub fn is_idempotent(&self) -> bool {
+        match self {
+            Command::Login { .. }
+            | Command::Update
+            | Command::HallOfFamePage { .. }
+            | Command::HallOfFameFortressPage { .. }
+            | Command::HallOfFameGroupPage { .. }
+            | Command::HallOfFameUnderworldPage { .. }
+            | Command::HallOfFamePetsPage { .. }
+            | Command::HallOfFameHellevatorPage { .. }
+            | Command::ViewPlayer { .. }
+            | Command::ViewGuild { .. }
+            | Command::ViewScrapbook
+            | Command::ViewPet { .. }
+            | Command::CheckNameAvailable { .. }
+            | Command::CheckArena
+            | Command::GuildGetFightableTargets
+            | Command::HellevatorViewGuildRanking
+            | Command::HellevatorPreviewRewards
+            | Command::ClaimablePreview { .. }
+            | Command::MessageOpen { .. } => true,
+            #[cfg(feature = "sso")]
+            Command::SSOLogin { .. } => true,
+            _ => false,
+        }
+    }
+
+    /// Checks if this command can spend mushrooms. This includes commands,
+    /// that are always paid with mushrooms (like `BuyBeer`), commands, that
+    /// have been told to pay with mushrooms (like `FinishQuest` with
+    /// `TimeSkip::Mushroom`) and commands, that spend a mushroom, if their
+    /// timer has not run out yet (like `Fight` with `use_mushroom`). Commands,
+    /// whose price depends on the item they act on (like `BuyShop`), are not
+    /// included
+    #[must_use]
+    pub fn uses_mushroom(&self) -> bool {
+        match self {
+            Command::Fight { use_mushroom, .. }
+            | Command::FightDungeon { use_mushroom, .. }
+            | Command::FortressNewEnemy { use_mushroom }
+            | Command::GuildPetBattle { use_mushroom }
+            | Command::HellevatorFight { use_mushroom }
+            | Command::HellevatorBuy { use_mushroom, .. }
+            | Command::HellevatorJoinHellAttack { use_mushroom, .. } => {
+                *use_mushroom
+            }
+            Command::FightTower { use_mush, .. }
+            | Command::FightPetDungeon { use_mush, .. } => *use_mush,
+            Command::FinishQuest { skip } => *skip == Some(TimeSkip::Mushroom),
+            Command::ExpeditionSkipWait { typ } => *typ == TimeSkip::Mushroom,
+            Command::FortressBuildFinish { mushrooms, .. }
+            | Command::FortressGemStoneSearchFinish { mushrooms }
+            | Command::UnderworldUpgradeStart { mushrooms, .. }
+            | Command::UnderworldUpgradeFinish { mushrooms, .. } => {
+                *mushrooms > 0
+            }
+            Command::BuyMount { mount } => mount.cost().mushrooms > 0,
+            Command::SpinWheelOfFortune { payment } => {
+                *payment == FortunePayment::Mushrooms
+            }
+            Command::RollDice { payment, .. } => {
+                *payment == RollDicePrice::Mushrooms
+            }
+            Command::Blacksmith { action, .. } => matches!(
+                action,
+                BlacksmithAction::SocketUpgradeWithMushrooms
+                    | BlacksmithAction::GemExtractWithMushrooms
+            ),
+            Command::GambleMushrooms { amount } => *amount > 0,
+            Command::BuyBeer
+            | Command::GuildLoadMushrooms
+            | Command::RefreshShop { .. }
+            | Command::ChangeItemLook { .. }
+            | Command::BuyGoldFrame => true,
+            _ => false,
+        }
+    }
+
     /// Returns the unencrypted string, that has to be send to the server to to
     /// perform the request
     #[allow(deprecated, clippy::useless_format)]
-    #[cfg(feature = "session")]
+    #[cfg(feature = "protocol")]
     pub(crate) fn request_string(
         &self,
+        options: &crate::protocol::ProtocolOptions,
     ) -> Result<String, crate::error::SFError> {
-        const APP_VERSION: &str = "2100000000000";
         use crate::{
             error::SFError,
             gamestate::dungeons::{LightDungeon, ShadowDungeon},
             misc::{sha1_hash, to_sf_string, HASH_CONST},
         };
+        let app_version = &options.app_version;
+        let platform = &options.platform;
+        let language = &options.language;
 
         Ok(match self {
             Command::Custom {
@@ -869,15 +1029,15 @@ impl Command {
                 let full_hash = sha1_hash(&format!("{pw_hash}{login_count}"));
                 format!(
                     "AccountLogin:{username}/{full_hash}/{login_count}/\
-                     unity3d_webglplayer//{APP_VERSION}///0/"
+                     {platform}//{app_version}///0/"
                 )
             }
             #[cfg(feature = "sso")]
             Command::SSOLogin {
                 uuid, character_id, ..
             } => format!(
-                "SFAccountCharLogin:{uuid}/{character_id}/unity3d_webglplayer/\
-                 /{APP_VERSION}"
+                "SFAccountCharLogin:{uuid}/{character_id}/{platform}//\
+                 {app_version}"
             ),
             Command::Register {
                 username,
@@ -889,7 +1049,7 @@ impl Command {
                 // TODO: Custom portrait
                 format!(
                     "AccountCreate:{username}/{password}/{username}@playa.sso/\
-                     {}/{}/{}/8,203,201,6,199,3,1,2,1/0//en",
+                     {}/{}/{}/8,203,201,6,199,3,1,2,1/0//{language}",
                     *gender as usize + 1,
                     *race as usize,
                     *class as usize + 1
@@ -937,7 +1097,7 @@ impl Command {
             Command::FinishQuest { skip } => {
                 format!(
                     "PlayerAdventureFinished:{}",
-                    skip.map(|a| a as u8).unwrap_or(0)
+                    skip.map_or(0, |a| a as u8)
                 )
             }
             Command::StartWork { hours } => format!("PlayerWorkStart:{hours}"),
@@ -1179,13 +1339,13 @@ impl Command {
                 format!("FortressBuildUnitStart:{}/{count}", *unit as usize + 1)
             }
             Command::FortressGemStoneSearch => {
-                format!("FortressGemstoneStart:",)
+                format!("FortressGemstoneStart:")
             }
             Command::FortressGemStoneSearchCancel => {
                 format!("FortressGemStoneStop:0")
             }
             Command::FortressGemStoneSearchFinish { mushrooms } => {
-                format!("FortressGemstoneFinished:{mushrooms}",)
+                format!("FortressGemstoneFinished:{mushrooms}")
             }
             Command::FortressAttack { soldiers } => {
                 format!("FortressAttack:{soldiers}")
@@ -1476,6 +1636,7 @@ macro_rules! generate_flag_enum {
         }
 
         impl Flag {
+            #[cfg_attr(not(feature = "protocol"), allow(dead_code))]
             pub(crate) fn code(self) -> &'static str {
                 match self {
                     $(
@@ -15,7 +15,14 @@ pub enum SFError {
     EmptyResponse,
     /// There was some error encountered when sending data to the server. Most
     /// likely the server, or your connection is down
-    ConnectionError,
+    ConnectionError {
+        /// What part of the communication failed
+        kind: ConnectionErrorKind,
+        /// The HTTP status code, if the server responded with one
+        status: Option<u16>,
+        /// The underlying error, if there is one
+        source: Option<Box<dyn Error + Send + Sync>>,
+    },
     /// Whatever the server send back was invalid. Could be because of features
     /// not yet supported, or a bug in the API
     ParsingError(&'static str, String),
@@ -37,11 +44,129 @@ pub enum SFError {
         /// The full array in debug print
         array: String,
     },
+    /// The command was not sent, because an `Interceptor` of the session
+    /// denied it. Contains the reason given by the interceptor
+    CommandDenied(String),
+    /// The server does not accept the session anymore, shortly after it has
+    /// been valid. This happens, when somebody else (usually a human in the
+    /// browser) logs in to the same character. Logging in again would kick
+    /// them out, which is why sessions never do that on their own. The server
+    /// does not tell this apart from an expired session, so this is only
+    /// returned, if `takeover_window` is set in the options
+    SessionTakenOver,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+/// The part of the communication with the server, that failed
+pub enum ConnectionErrorKind {
+    /// The server did not respond in time
+    Timeout,
+    /// No connection to the server could be established
+    Connect,
+    /// The response could not be received, or decoded
+    Decode,
+    /// The server responded with a non success status code
+    Status,
+    /// Anything else
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+/// A rough classification of errors, based on what has to be done to recover
+/// from them
+pub enum ErrorClass {
+    /// The server could not be reached, or did not answer properly. Trying
+    /// again later might work
+    Connection,
+    /// The session is not valid anymore, because it expired. Logging in again
+    /// should fix this
+    Session,
+    /// Somebody else logged in to the character. Logging in again would work,
+    /// but would also kick them out. See `SFError::SessionTakenOver`
+    TakenOver,
+    /// Anything else. This is either an ingame error (not enough silver,
+    /// etc.), or an error on our side. Sending the same command again will
+    /// not help
+    Game,
+}
+
+/// The key, that the server responds with, when it does not know the crypto
+/// id of a session (anymore). Responses containing it are turned into a
+/// `ServerError` with this as the message
+pub(crate) const SESSION_INVALID: &str = "cryptoid not found";
+
+impl SFError {
+    /// Creates a `ConnectionError` of the given kind without any more details
+    #[must_use]
+    pub fn connection(kind: ConnectionErrorKind) -> Self {
+        SFError::ConnectionError {
+            kind,
+            status: None,
+            source: None,
+        }
+    }
+
+    /// Creates a `ConnectionError` for a response with a non success status
+    /// code
+    #[must_use]
+    pub fn http_status(status: u16) -> Self {
+        SFError::ConnectionError {
+            kind: ConnectionErrorKind::Status,
+            status: Some(status),
+            source: None,
+        }
+    }
+
+    /// Classifies the error based on what has to be done to recover from it
+    #[must_use]
+    pub fn class(&self) -> ErrorClass {
+        match self {
+            SFError::ConnectionError { .. } => ErrorClass::Connection,
+            SFError::SessionTakenOver => ErrorClass::TakenOver,
+            // The server sends empty responses to requests from sessions, that
+            // it does not know about
+            SFError::EmptyResponse => ErrorClass::Session,
+            SFError::ServerError(msg) if msg == SESSION_INVALID => {
+                ErrorClass::Session
+            }
+            _ => ErrorClass::Game,
+        }
+    }
+}
+
+#[cfg(feature = "session")]
+impl From<reqwest::Error> for SFError {
+    fn from(value: reqwest::Error) -> Self {
+        let kind = if value.is_timeout() {
+            ConnectionErrorKind::Timeout
+        } else if value.is_connect() {
+            ConnectionErrorKind::Connect
+        } else if value.is_decode() || value.is_body() {
+            ConnectionErrorKind::Decode
+        } else if value.is_status() {
+            ConnectionErrorKind::Status
+        } else {
+            ConnectionErrorKind::Other
+        };
+        SFError::ConnectionError {
+            kind,
+            status: value.status().map(|a| a.as_u16()),
+            source: Some(Box::new(value)),
+        }
+    }
 }
 
 impl Error for SFError {
     fn source(&self) -> Option<&(dyn Error + 'static)> {
-        None
+        match self {
+            SFError::ConnectionError {
+                source: Some(source),
+                ..
+            } => Some(source.as_ref()),
+            _ => None,
+        }
     }
 
     fn description(&self) -> &'static str {
@@ -62,8 +187,14 @@ impl Display for SFError {
             SFError::EmptyResponse => {
                 f.write_str("Received an empty response from the server")
             }
-            SFError::ConnectionError => {
-                f.write_str("Could not communicate with the server")
+            SFError::ConnectionError { kind, status, .. } => {
+                f.write_str("Could not communicate with the server")?;
+                match status {
+                    Some(status) => f.write_fmt(format_args!(
+                        " ({kind:?}, status {status})"
+                    )),
+                    None => f.write_fmt(format_args!(" ({kind:?})")),
+                }
             }
             SFError::ParsingError(name, value) => f.write_fmt(format_args!(
                 "Error parsing the server response because {name} had an \
@@ -81,6 +212,12 @@ impl Display for SFError {
                      the response is too short. The response is: {array}"
                 ))
             }
+            SFError::CommandDenied(reason) => f.write_fmt(format_args!(
+                "The command was denied before sending: {reason}"
+            )),
+            SFError::SessionTakenOver => {
+                f.write_str("Somebody else has logged in to this character")
+            }
         }
     }
 }
@@ -0,0 +1,268 @@
+use std::{
+    any::Any,
+    cell::RefCell,
+    ffi::{c_char, CString},
+    panic::{catch_unwind, AssertUnwindSafe},
+    ptr,
+};
+
+use log::debug;
+
+use super::{SfArena, SfAttributes, SfCurrentAction, SfDungeonProgress};
+use crate::error::{ConnectionErrorKind, SFError};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The kind of error, that occurred in the last failed call. Apart from `Ok`
+/// and `InvalidArgument`, these mirror the variants of `SFError`. The values
+/// are stable and new codes will only ever be appended
+pub enum SfErrorCode {
+    /// No error has occurred
+    Ok = 0,
+    /// See `SFError::InvalidRequest`
+    InvalidRequest = 1,
+    /// See `SFError::EmptyResponse`
+    EmptyResponse = 2,
+    /// See `SFError::ConnectionError`
+    ConnectionError = 3,
+    /// See `SFError::ParsingError`
+    ParsingError = 4,
+    /// See `SFError::ServerError`. This is what you get for ingame errors,
+    /// like a wrong password, or not having enough silver
+    ServerError = 5,
+    /// See `SFError::UnsupportedVersion`
+    UnsupportedVersion = 6,
+    /// See `SFError::TooShortResponse`
+    TooShortResponse = 7,
+    /// One of the arguments was null, not valid UTF-8, or otherwise invalid
+    InvalidArgument = 8,
+    /// The library encountered a bug and panicked. The panic did not cross
+    /// the FFI boundary, but any handle used in the call might now be in an
+    /// inconsistent state
+    Panic = 9,
+    /// See `SFError::CommandDenied`
+    CommandDenied = 10,
+    /// See `SFError::SessionTakenOver`
+    SessionTakenOver = 11,
+}
+
+impl From<&SFError> for SfErrorCode {
+    fn from(value: &SFError) -> Self {
+        match value {
+            SFError::InvalidRequest(_) => SfErrorCode::InvalidRequest,
+            SFError::EmptyResponse => SfErrorCode::EmptyResponse,
+            SFError::ConnectionError { .. } => SfErrorCode::ConnectionError,
+            SFError::ParsingError(..) => SfErrorCode::ParsingError,
+            SFError::ServerError(_) => SfErrorCode::ServerError,
+            SFError::UnsupportedVersion(_) => SfErrorCode::UnsupportedVersion,
+            SFError::TooShortResponse { .. } => SfErrorCode::TooShortResponse,
+            SFError::CommandDenied(_) => SfErrorCode::CommandDenied,
+            SFError::SessionTakenOver => SfErrorCode::SessionTakenOver,
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+/// The part of the communication with the server, that failed. See
+/// `ConnectionErrorKind`
+pub enum SfConnectionErrorKind {
+    /// The last error was not a `ConnectionError`
+    None = 0,
+    /// The server did not respond in time
+    Timeout = 1,
+    /// No connection to the server could be established
+    Connect = 2,
+    /// The response could not be received, or decoded
+    Decode = 3,
+    /// The server responded with a non success status code
+    Status = 4,
+    /// Anything else
+    Other = 5,
+}
+
+impl From<ConnectionErrorKind> for SfConnectionErrorKind {
+    fn from(value: ConnectionErrorKind) -> Self {
+        match value {
+            ConnectionErrorKind::Timeout => SfConnectionErrorKind::Timeout,
+            ConnectionErrorKind::Connect => SfConnectionErrorKind::Connect,
+            ConnectionErrorKind::Decode => SfConnectionErrorKind::Decode,
+            ConnectionErrorKind::Status => SfConnectionErrorKind::Status,
+            _ => SfConnectionErrorKind::Other,
+        }
+    }
+}
+
+struct LastError {
+    code: SfErrorCode,
+    message: CString,
+    connection_kind: SfConnectionErrorKind,
+    http_status: u16,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<LastError>> =
+        const { RefCell::new(None) };
+}
+
+fn store_error(
+    code: SfErrorCode,
+    message: &str,
+    connection_kind: SfConnectionErrorKind,
+    http_status: u16,
+) {
+    debug!("FFI error {code:?}: {message}");
+    // The message is only used for display purposes, so we can just drop
+    // anything, that would be invalid in a C string
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|e| {
+        *e.borrow_mut() = Some(LastError {
+            code,
+            message,
+            connection_kind,
+            http_status,
+        });
+    });
+}
+
+/// Stores the error as the last error of this thread
+pub(super) fn set_last_error(code: SfErrorCode, message: &str) {
+    store_error(code, message, SfConnectionErrorKind::None, 0);
+}
+
+/// Stores the `SFError` as the last error of this thread and returns its code
+pub(super) fn set_sf_error(name: &str, err: &SFError) -> SfErrorCode {
+    let code = err.into();
+    let (kind, status) = match err {
+        SFError::ConnectionError { kind, status, .. } => {
+            ((*kind).into(), status.unwrap_or_default())
+        }
+        _ => (SfConnectionErrorKind::None, 0),
+    };
+    store_error(code, &format!("{name}: {err}"), kind, status);
+    code
+}
+
+/// Stores an `InvalidArgument` error as the last error of this thread
+pub(super) fn set_invalid_argument(name: &str) {
+    set_last_error(
+        SfErrorCode::InvalidArgument,
+        &format!("{name}: Invalid argument"),
+    );
+}
+
+/// The value, that is returned to C, if a function panicked
+pub(super) trait FfiDefault {
+    fn ffi_default() -> Self;
+}
+
+impl<T> FfiDefault for *mut T {
+    fn ffi_default() -> Self {
+        ptr::null_mut()
+    }
+}
+
+impl<T> FfiDefault for *const T {
+    fn ffi_default() -> Self {
+        ptr::null()
+    }
+}
+
+macro_rules! ffi_default_impl {
+    ($($t:ty),*) => {
+        $(
+            impl FfiDefault for $t {
+                fn ffi_default() -> Self {
+                    Self::default()
+                }
+            }
+        )*
+    };
+}
+
+ffi_default_impl!(
+    (),
+    bool,
+    u16,
+    u32,
+    u64,
+    i64,
+    usize,
+    SfAttributes,
+    SfCurrentAction,
+    SfArena,
+    SfDungeonProgress
+);
+
+/// Extracts the message from the payload of a panic
+pub(super) fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = payload.downcast_ref::<&str>() {
+        msg
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg
+    } else {
+        "unknown panic"
+    }
+}
+
+/// Runs the body of an exported function. Unwinding across the FFI boundary
+/// is undefined behaviour, so any panic is caught here, stored as a `Panic`
+/// error and turned into the default return value of the function
+pub(super) fn ffi_guard<T: FfiDefault>(
+    name: &str,
+    body: impl FnOnce() -> T,
+) -> T {
+    catch_unwind(AssertUnwindSafe(body)).unwrap_or_else(|payload| {
+        set_last_error(
+            SfErrorCode::Panic,
+            &format!("{name}: {}", panic_message(payload.as_ref())),
+        );
+        T::ffi_default()
+    })
+}
+
+/// Returns the code of the last error, that occurred on this thread. This is
+/// only meaningful directly after a function signaled a failure (by returning
+/// null, or false). Within a completion callback, this is the error of the
+/// request, that the callback is called for
+#[no_mangle]
+pub extern "C" fn sf_last_error_code() -> SfErrorCode {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(SfErrorCode::Ok, |a| a.code))
+}
+
+/// Returns a human readable description of the last error, that occurred on
+/// this thread, or null, if there was none. The string is owned by the library
+/// and stays valid until the next error occurs on this thread
+#[no_mangle]
+pub extern "C" fn sf_last_error_message() -> *const c_char {
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |a| a.message.as_ptr())
+    })
+}
+
+/// Returns what part of the communication failed, if the last error of this
+/// thread was a `ConnectionError`. Otherwise this returns `None`
+#[no_mangle]
+pub extern "C" fn sf_last_error_connection_kind() -> SfConnectionErrorKind {
+    LAST_ERROR.with(|e| {
+        e.borrow()
+            .as_ref()
+            .map_or(SfConnectionErrorKind::None, |a| a.connection_kind)
+    })
+}
+
+/// Returns the HTTP status code, that the server responded with, if the last
+/// error of this thread was caused by it. Otherwise this returns 0
+#[no_mangle]
+pub extern "C" fn sf_last_error_http_status() -> u16 {
+    LAST_ERROR.with(|e| e.borrow().as_ref().map_or(0, |a| a.http_status))
+}
+
+/// Resets the last error of this thread, so that `sf_last_error_code` returns
+/// `Ok` again
+#[no_mangle]
+pub extern "C" fn sf_last_error_clear() {
+    LAST_ERROR.with(|e| *e.borrow_mut() = None);
+}
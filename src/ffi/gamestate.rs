@@ -0,0 +1,619 @@
+use std::{ffi::c_char, ptr};
+
+use enum_map::EnumMap;
+use num_traits::FromPrimitive;
+
+use super::{
+    ffi_guard, owned_c_string, set_invalid_argument, set_last_error,
+    set_sf_error, timestamp_ms, SfErrorCode, SfStr,
+};
+use crate::{
+    command::AttributeType,
+    gamestate::{
+        dungeons::{Dungeon, DungeonProgress, LightDungeon, ShadowDungeon},
+        fortress::FortressResourceType,
+        tavern::CurrentAction,
+        underworld::UnderWorldResourceType,
+        GameState,
+    },
+    misc::EnumMapGet,
+    response::Response,
+};
+
+/// Creates a new gamestate from the login response of a session. Returns null,
+/// if the response does not contain a full character (see
+/// `sf_last_error_code`). The response is not
+/// consumed and still has to be freed by the caller
+///
+/// # Safety
+/// `response` has to be null, or a valid response handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_gamestate_new(
+    response: *const Response,
+) -> *mut GameState {
+    ffi_guard("sf_gamestate_new", || {
+        let Some(response) = response.as_ref() else {
+            set_invalid_argument("sf_gamestate_new");
+            return ptr::null_mut();
+        };
+        match GameState::new(response.clone()) {
+            Ok(gs) => Box::into_raw(Box::new(gs)),
+            Err(e) => {
+                set_sf_error("sf_gamestate_new", &e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Updates the gamestate with the response of any command. Returns false, if
+/// the response could not be applied. The response is not consumed
+///
+/// # Safety
+/// `gs` and `response` have to be null, or valid handles
+#[no_mangle]
+pub unsafe extern "C" fn sf_gamestate_update(
+    gs: *mut GameState,
+    response: *const Response,
+) -> bool {
+    ffi_guard("sf_gamestate_update", || {
+        let (Some(gs), Some(response)) = (gs.as_mut(), response.as_ref())
+        else {
+            set_invalid_argument("sf_gamestate_update");
+            return false;
+        };
+        match gs.update(response) {
+            Ok(()) => true,
+            Err(e) => {
+                set_sf_error("sf_gamestate_update", &e);
+                false
+            }
+        }
+    })
+}
+
+/// Serializes the full gamestate as JSON. The returned string has to be freed
+/// with `sf_string_free`. Returns null, if `gs` is null
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_gamestate_to_json(
+    gs: *const GameState,
+) -> *mut c_char {
+    ffi_guard("sf_gamestate_to_json", || {
+        let Some(gs) = gs.as_ref() else {
+            set_invalid_argument("sf_gamestate_to_json");
+            return ptr::null_mut();
+        };
+        match serde_json::to_string(gs) {
+            Ok(json) => owned_c_string(json),
+            Err(e) => {
+                set_last_error(
+                    SfErrorCode::ParsingError,
+                    &format!("sf_gamestate_to_json: Could not serialize: {e}"),
+                );
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Frees a gamestate
+///
+/// # Safety
+/// `gs` has to be null, or a gamestate handle, that has not yet been freed
+#[no_mangle]
+pub unsafe extern "C" fn sf_gamestate_free(gs: *mut GameState) {
+    ffi_guard("sf_gamestate_free", || {
+        if gs.is_null() {
+            return;
+        }
+        drop(Box::from_raw(gs));
+    });
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+#[allow(missing_docs)]
+/// One value per attribute
+pub struct SfAttributes {
+    pub strength: u32,
+    pub dexterity: u32,
+    pub intelligence: u32,
+    pub constitution: u32,
+    pub luck: u32,
+}
+
+impl From<&EnumMap<AttributeType, u32>> for SfAttributes {
+    fn from(value: &EnumMap<AttributeType, u32>) -> Self {
+        Self {
+            strength: *value.get(AttributeType::Strength),
+            dexterity: *value.get(AttributeType::Dexterity),
+            intelligence: *value.get(AttributeType::Intelligence),
+            constitution: *value.get(AttributeType::Constitution),
+            luck: *value.get(AttributeType::Luck),
+        }
+    }
+}
+
+/// The name of the character
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_character_name(gs: *const GameState) -> SfStr {
+    ffi_guard("sf_character_name", || {
+        gs.as_ref()
+            .map_or_else(SfStr::missing, |gs| gs.character.name.as_str().into())
+    })
+}
+
+/// The level of the character
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_character_level(gs: *const GameState) -> u16 {
+    ffi_guard("sf_character_level", || {
+        gs.as_ref().map_or(0, |gs| gs.character.level)
+    })
+}
+
+/// The silver of the character. 100 silver = 1 gold
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_character_silver(gs: *const GameState) -> u64 {
+    ffi_guard("sf_character_silver", || {
+        gs.as_ref().map_or(0, |gs| gs.character.silver)
+    })
+}
+
+/// The mushrooms of the character
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_character_mushrooms(gs: *const GameState) -> u32 {
+    ffi_guard("sf_character_mushrooms", || {
+        gs.as_ref().map_or(0, |gs| gs.character.mushrooms)
+    })
+}
+
+/// The base attributes of the character without any equipment, or other
+/// boosts
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_character_attribute_basis(
+    gs: *const GameState,
+) -> SfAttributes {
+    ffi_guard("sf_character_attribute_basis", || {
+        gs.as_ref().map_or_else(SfAttributes::default, |gs| {
+            (&gs.character.attribute_basis).into()
+        })
+    })
+}
+
+/// All bonus attributes of the character from equipment/pets/potions
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_character_attribute_additions(
+    gs: *const GameState,
+) -> SfAttributes {
+    ffi_guard("sf_character_attribute_additions", || {
+        gs.as_ref().map_or_else(SfAttributes::default, |gs| {
+            (&gs.character.attribute_additions).into()
+        })
+    })
+}
+
+/// How many seconds the character still has left to do adventures
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_tavern_thirst(gs: *const GameState) -> u32 {
+    ffi_guard("sf_tavern_thirst", || {
+        gs.as_ref()
+            .map_or(0, |gs| gs.tavern.thirst_for_adventure_sec)
+    })
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+/// One of the three quests in the tavern
+pub struct SfQuest {
+    /// The length of this quest in sec (without item enchantment)
+    pub base_length: u32,
+    /// The silver reward for this quest (without item enchantment)
+    pub base_silver: u32,
+    /// The xp reward for this quest (without item enchantment)
+    pub base_experience: u32,
+    /// The id of the location, where this quest takes place
+    pub location_id: u32,
+    /// The id of the enemy you fight in this quest
+    pub monster_id: u16,
+    /// Whether this quest rewards an item
+    pub has_item: bool,
+}
+
+/// Reads the quest at `idx` (0-2) into `out`. Returns false, if the index is
+/// out of bounds
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle. `out` has to be null, or
+/// point to writable memory for one `SfQuest`
+#[no_mangle]
+pub unsafe extern "C" fn sf_tavern_quest(
+    gs: *const GameState,
+    idx: usize,
+    out: *mut SfQuest,
+) -> bool {
+    ffi_guard("sf_tavern_quest", || {
+        let (Some(gs), Some(out)) = (gs.as_ref(), out.as_mut()) else {
+            set_invalid_argument("sf_tavern_quest");
+            return false;
+        };
+        let Some(quest) = gs.tavern.quests.get(idx) else {
+            set_last_error(
+                SfErrorCode::InvalidArgument,
+                "sf_tavern_quest: The quest index is out of bounds",
+            );
+            return false;
+        };
+        *out = SfQuest {
+            base_length: quest.base_length,
+            base_silver: quest.base_silver,
+            base_experience: quest.base_experience,
+            location_id: quest.location_id as u32,
+            monster_id: quest.monster_id,
+            has_item: quest.item.is_some(),
+        };
+        true
+    })
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+/// The kind of thing the character is currently doing
+pub enum SfActionKind {
+    #[default]
+    Idle,
+    CityGuard,
+    Quest,
+    Expedition,
+    Unknown,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+/// The thing the character is currently doing
+pub struct SfCurrentAction {
+    /// What the character is doing
+    pub kind: SfActionKind,
+    /// The amount of hours for `CityGuard`, or the quest index for `Quest`
+    pub value: u8,
+    /// The time at which the action is finished in milliseconds since the
+    /// unix epoch, or 0, if there is no such time
+    pub busy_until: i64,
+}
+
+/// The thing the character is currently doing (questing, working, etc.)
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_tavern_current_action(
+    gs: *const GameState,
+) -> SfCurrentAction {
+    ffi_guard("sf_tavern_current_action", || {
+        let Some(gs) = gs.as_ref() else {
+            return SfCurrentAction::default();
+        };
+        let (kind, value, busy_until) = match gs.tavern.current_action {
+            CurrentAction::Idle => (SfActionKind::Idle, 0, None),
+            CurrentAction::CityGuard { hours, busy_until } => {
+                (SfActionKind::CityGuard, hours, Some(busy_until))
+            }
+            CurrentAction::Quest {
+                quest_idx,
+                busy_until,
+            } => (SfActionKind::Quest, quest_idx, Some(busy_until)),
+            CurrentAction::Expedition => (SfActionKind::Expedition, 0, None),
+            CurrentAction::Unkown(busy_until) => {
+                (SfActionKind::Unknown, 0, busy_until)
+            }
+        };
+        SfCurrentAction {
+            kind,
+            value,
+            busy_until: timestamp_ms(busy_until),
+        }
+    })
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+/// The arena, that a player can fight other players in
+pub struct SfArena {
+    /// The ids of the enemies currently available in the arena
+    pub enemy_ids: [u32; 3],
+    /// The time at which the player will be able to fight for free again in
+    /// milliseconds since the unix epoch, or 0, if there is no such time
+    pub next_free_fight: i64,
+    /// The amount of fights this character has already fought today, that
+    /// gave xp. 0-10
+    pub fights_for_xp: u8,
+}
+
+/// The current state of the arena
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_arena(gs: *const GameState) -> SfArena {
+    ffi_guard("sf_arena", || {
+        gs.as_ref().map_or_else(SfArena::default, |gs| SfArena {
+            enemy_ids: gs.arena.enemy_ids,
+            next_free_fight: timestamp_ms(gs.arena.next_free_fight),
+            fights_for_xp: gs.arena.fights_for_xp,
+        })
+    })
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+/// The category of a dungeon. Functions take this as its `u32` value, so that
+/// C can never hand us a value, that is not a valid variant
+pub enum SfDungeonType {
+    Light = 0,
+    Shadow = 1,
+}
+
+impl TryFrom<u32> for SfDungeonType {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(SfDungeonType::Light),
+            1 => Ok(SfDungeonType::Shadow),
+            _ => Err(value),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+/// The state a dungeon can be in
+pub enum SfDungeonState {
+    /// The dungeon id does not exist
+    #[default]
+    Invalid,
+    Locked,
+    Open,
+    Finished,
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+/// The current state of a dungeon
+pub struct SfDungeonProgress {
+    /// The state of the dungeon
+    pub state: SfDungeonState,
+    /// The amount of enemies already finished, if the dungeon is `Open`
+    pub finished: u16,
+}
+
+/// Converts the C representation of a dungeon into the actual dungeon. `typ`
+/// is a `SfDungeonType` and the ids are the same as the discriminants of
+/// `LightDungeon`/`ShadowDungeon`
+pub(super) fn dungeon_from_id(typ: u32, id: u32) -> Option<Dungeon> {
+    match SfDungeonType::try_from(typ).ok()? {
+        SfDungeonType::Light => LightDungeon::from_u32(id).map(Dungeon::Light),
+        SfDungeonType::Shadow => {
+            ShadowDungeon::from_u32(id).map(Dungeon::Shadow)
+        }
+    }
+}
+
+/// The progress the character has made in the dungeon. `typ` is a
+/// `SfDungeonType` and the id is the same as the value of the
+/// `LightDungeon`/`ShadowDungeon` variants. The state is `Invalid`, if any of
+/// the arguments is invalid
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_dungeon_progress(
+    gs: *const GameState,
+    typ: u32,
+    id: u32,
+) -> SfDungeonProgress {
+    ffi_guard("sf_dungeon_progress", || {
+        let (Some(gs), Some(dungeon)) = (gs.as_ref(), dungeon_from_id(typ, id))
+        else {
+            set_invalid_argument("sf_dungeon_progress");
+            return SfDungeonProgress::default();
+        };
+        let (state, finished) = match gs.dungeons.progress(dungeon) {
+            DungeonProgress::Locked => (SfDungeonState::Locked, 0),
+            DungeonProgress::Open { finished } => {
+                (SfDungeonState::Open, finished)
+            }
+            DungeonProgress::Finished => (SfDungeonState::Finished, 0),
+        };
+        SfDungeonProgress { state, finished }
+    })
+}
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+/// The amount of a resource and the state of the building producing it
+pub struct SfResource {
+    /// The amount currently available to spend
+    pub current: u64,
+    /// The maximum amount, that can be stored
+    pub limit: u64,
+    /// The amount the production building has produced, that can be collected
+    pub collectable: u64,
+    /// The maximum amount the production building can store
+    pub collectable_limit: u64,
+    /// The amount the production building produces per hour
+    pub per_hour: u64,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+/// A resource, that the fortress produces. Functions take this as its `u32`
+/// value
+pub enum SfFortressResource {
+    Wood = 0,
+    Stone = 1,
+    Experience = 2,
+}
+
+impl TryFrom<u32> for SfFortressResource {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(SfFortressResource::Wood),
+            1 => Ok(SfFortressResource::Stone),
+            2 => Ok(SfFortressResource::Experience),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Reads the fortress resource into `out`. `typ` is a `SfFortressResource`.
+/// Returns false, if any of the arguments is invalid, or the fortress has not
+/// been unlocked
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle. `out` has to be null, or
+/// point to writable memory for one `SfResource`
+#[no_mangle]
+pub unsafe extern "C" fn sf_fortress_resource(
+    gs: *const GameState,
+    typ: u32,
+    out: *mut SfResource,
+) -> bool {
+    ffi_guard("sf_fortress_resource", || {
+        let (Some(gs), Ok(typ), Some(out)) =
+            (gs.as_ref(), SfFortressResource::try_from(typ), out.as_mut())
+        else {
+            set_invalid_argument("sf_fortress_resource");
+            return false;
+        };
+        let Some(fortress) = &gs.fortress else {
+            set_last_error(
+                SfErrorCode::InvalidArgument,
+                "sf_fortress_resource: The fortress has not been unlocked",
+            );
+            return false;
+        };
+        let typ = match typ {
+            SfFortressResource::Wood => FortressResourceType::Wood,
+            SfFortressResource::Stone => FortressResourceType::Stone,
+            SfFortressResource::Experience => FortressResourceType::Experience,
+        };
+        let resource = fortress.resources.get(typ);
+        *out = SfResource {
+            current: resource.current,
+            limit: resource.limit,
+            collectable: resource.production.last_collectable,
+            collectable_limit: resource.production.limit,
+            per_hour: resource.production.per_hour,
+        };
+        true
+    })
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(missing_docs)]
+/// A resource, that the underworld produces. Functions take this as its `u32`
+/// value
+pub enum SfUnderworldResource {
+    Souls = 0,
+    Silver = 1,
+    ThirstForAdventure = 2,
+}
+
+impl TryFrom<u32> for SfUnderworldResource {
+    type Error = u32;
+
+    fn try_from(value: u32) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(SfUnderworldResource::Souls),
+            1 => Ok(SfUnderworldResource::Silver),
+            2 => Ok(SfUnderworldResource::ThirstForAdventure),
+            _ => Err(value),
+        }
+    }
+}
+
+/// Reads the underworld resource into `out`. `typ` is a
+/// `SfUnderworldResource`. Only souls have a `current` and `limit`, the other
+/// resources only contain production info. Returns false, if any of the
+/// arguments is invalid, or the underworld has not been unlocked
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle. `out` has to be null, or
+/// point to writable memory for one `SfResource`
+#[no_mangle]
+pub unsafe extern "C" fn sf_underworld_resource(
+    gs: *const GameState,
+    typ: u32,
+    out: *mut SfResource,
+) -> bool {
+    ffi_guard("sf_underworld_resource", || {
+        let (Some(gs), Ok(typ), Some(out)) = (
+            gs.as_ref(),
+            SfUnderworldResource::try_from(typ),
+            out.as_mut(),
+        ) else {
+            set_invalid_argument("sf_underworld_resource");
+            return false;
+        };
+        let Some(underworld) = &gs.underworld else {
+            set_last_error(
+                SfErrorCode::InvalidArgument,
+                "sf_underworld_resource: The underworld has not been unlocked",
+            );
+            return false;
+        };
+        let (typ, current, limit) = match typ {
+            SfUnderworldResource::Souls => (
+                UnderWorldResourceType::Souls,
+                underworld.souls_current,
+                underworld.souls_limit,
+            ),
+            SfUnderworldResource::Silver => {
+                (UnderWorldResourceType::Silver, 0, 0)
+            }
+            SfUnderworldResource::ThirstForAdventure => {
+                (UnderWorldResourceType::ThirstForAdventure, 0, 0)
+            }
+        };
+        let production = underworld.production.get(typ);
+        *out = SfResource {
+            current,
+            limit,
+            collectable: production.last_collectable,
+            collectable_limit: production.limit,
+            per_hour: production.per_hour,
+        };
+        true
+    })
+}
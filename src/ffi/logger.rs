@@ -0,0 +1,144 @@
+use std::{
+    ffi::c_void,
+    sync::{OnceLock, PoisonError, RwLock},
+};
+
+use log::{LevelFilter, Log, Metadata, Record};
+
+use super::{ffi_guard, set_last_error, SfErrorCode, SfStr};
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[allow(missing_docs)]
+/// The severity of a log message. When used as a filter, every message with a
+/// level up to, and including this one is passed on
+pub enum SfLogLevel {
+    Off = 0,
+    Error = 1,
+    Warn = 2,
+    Info = 3,
+    Debug = 4,
+    Trace = 5,
+}
+
+impl From<SfLogLevel> for LevelFilter {
+    fn from(value: SfLogLevel) -> Self {
+        match value {
+            SfLogLevel::Off => LevelFilter::Off,
+            SfLogLevel::Error => LevelFilter::Error,
+            SfLogLevel::Warn => LevelFilter::Warn,
+            SfLogLevel::Info => LevelFilter::Info,
+            SfLogLevel::Debug => LevelFilter::Debug,
+            SfLogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+impl From<log::Level> for SfLogLevel {
+    fn from(value: log::Level) -> Self {
+        match value {
+            log::Level::Error => SfLogLevel::Error,
+            log::Level::Warn => SfLogLevel::Warn,
+            log::Level::Info => SfLogLevel::Info,
+            log::Level::Debug => SfLogLevel::Debug,
+            log::Level::Trace => SfLogLevel::Trace,
+        }
+    }
+}
+
+/// Called for every log message of the library. `target` is the module, that
+/// logged the message. Both strings are only valid for the duration of the
+/// call. This can be called from any thread at any time, so the callback has
+/// to be thread safe. It must not call `sf_set_log_callback`
+pub type SfLogCallback = Option<
+    extern "C" fn(
+        level: SfLogLevel,
+        target: SfStr,
+        message: SfStr,
+        user_data: *mut c_void,
+    ),
+>;
+
+struct LogTarget {
+    callback: extern "C" fn(SfLogLevel, SfStr, SfStr, *mut c_void),
+    user_data: *mut c_void,
+}
+
+// SAFETY: The caller of `sf_set_log_callback` has promised us, that the
+// callback and its user data can be used from any thread
+unsafe impl Send for LogTarget {
+}
+unsafe impl Sync for LogTarget {
+}
+
+static LOG_TARGET: RwLock<Option<LogTarget>> = RwLock::new(None);
+
+/// Forwards all records to the callback in `LOG_TARGET`
+struct CallbackLogger;
+
+static LOGGER: CallbackLogger = CallbackLogger;
+
+impl Log for CallbackLogger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record<'_>) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        let target = LOG_TARGET.read().unwrap_or_else(PoisonError::into_inner);
+        let Some(target) = target.as_ref() else {
+            return;
+        };
+        let message = record.args().to_string();
+        (target.callback)(
+            record.level().into(),
+            record.target().into(),
+            message.as_str().into(),
+            target.user_data,
+        );
+    }
+
+    fn flush(&self) {
+    }
+}
+
+/// Sends all log messages of the library up to the given level to the
+/// callback. Passing a null callback, or `Off` as the level disables logging
+/// again. Once this returns, the previous callback will not be called anymore.
+/// Returns false, if another logger has already been installed in this process
+/// (for example by Rust code, that links against this library)
+///
+/// # Safety
+/// The callback has to be safe to call from any thread with the given
+/// `user_data` until it is replaced by another call to this function
+#[no_mangle]
+pub unsafe extern "C" fn sf_set_log_callback(
+    level: SfLogLevel,
+    callback: SfLogCallback,
+    user_data: *mut c_void,
+) -> bool {
+    ffi_guard("sf_set_log_callback", || {
+        static INSTALLED: OnceLock<bool> = OnceLock::new();
+        if !*INSTALLED.get_or_init(|| log::set_logger(&LOGGER).is_ok()) {
+            set_last_error(
+                SfErrorCode::InvalidRequest,
+                "sf_set_log_callback: Another logger is already installed",
+            );
+            return false;
+        }
+
+        let target = callback.map(|callback| LogTarget {
+            callback,
+            user_data,
+        });
+        let level = match target {
+            Some(_) => level.into(),
+            None => LevelFilter::Off,
+        };
+        *LOG_TARGET.write().unwrap_or_else(PoisonError::into_inner) = target;
+        log::set_max_level(level);
+        true
+    })
+}
@@ -0,0 +1,126 @@
+//! The C ABI of this crate. Everything in here is only compiled with the `ffi`
+//! feature and is meant to be consumed through the header, that `build.rs`
+//! generates via cbindgen. Rust users should never need anything in here.
+//!
+//! All handles handed out by these functions are owned by the caller and have
+//! to be released with the matching `sf_*_free` function. Everything, that
+//! talks to the server, is driven by a `SfRuntime`, which should be created
+//! once with `sf_runtime_new` and shared by all sessions. Functions signal
+//! failure by returning null, or false. The reason for the failure can then be
+//! read with `sf_last_error_code` and `sf_last_error_message`. Panics never
+//! unwind into the caller. They are reported as a `Panic` error instead.
+
+mod error;
+mod gamestate;
+mod logger;
+mod request;
+mod response;
+mod runtime;
+mod session;
+mod simulate;
+#[cfg(feature = "sso")]
+mod sso;
+
+use std::{
+    ffi::{c_char, CStr, CString},
+    ptr,
+};
+
+use chrono::{DateTime, Local, NaiveDateTime};
+pub use error::*;
+pub use gamestate::*;
+pub use logger::*;
+pub use request::*;
+pub use response::*;
+pub use runtime::*;
+pub use session::*;
+pub use simulate::*;
+#[cfg(feature = "sso")]
+pub use sso::*;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+/// A string, that is borrowed from one of the handles. It is NOT nul
+/// terminated and only valid as long as the handle it was read from is alive
+/// and has not been modified. A missing value is signaled by a null `ptr`
+pub struct SfStr {
+    /// The start of the UTF-8 encoded string
+    pub ptr: *const c_char,
+    /// The length of the string in bytes
+    pub len: usize,
+}
+
+impl SfStr {
+    fn missing() -> Self {
+        Self {
+            ptr: ptr::null(),
+            len: 0,
+        }
+    }
+}
+
+impl FfiDefault for SfStr {
+    fn ffi_default() -> Self {
+        Self::missing()
+    }
+}
+
+impl From<&str> for SfStr {
+    fn from(value: &str) -> Self {
+        Self {
+            ptr: value.as_ptr().cast(),
+            len: value.len(),
+        }
+    }
+}
+
+/// Converts a nul terminated C string into a `&str`. Returns `None`, if the
+/// pointer is null, or the string is not valid UTF-8
+///
+/// # Safety
+/// `ptr` has to either be null, or point to a valid nul terminated string,
+/// that outlives `'a`
+unsafe fn c_str<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    CStr::from_ptr(ptr).to_str().ok()
+}
+
+/// Hands ownership of the string to the caller. The string has to be freed
+/// with `sf_string_free`. Returns null, if the string contains a nul byte
+fn owned_c_string(val: String) -> *mut c_char {
+    CString::new(val).map_or(ptr::null_mut(), CString::into_raw)
+}
+
+/// Frees a string, that was returned by one of the `sf_*` functions
+///
+/// # Safety
+/// `val` has to be null, or a string returned by this library, that has not
+/// yet been freed
+#[no_mangle]
+pub unsafe extern "C" fn sf_string_free(val: *mut c_char) {
+    ffi_guard("sf_string_free", || {
+        if val.is_null() {
+            return;
+        }
+        drop(CString::from_raw(val));
+    });
+}
+
+/// Converts a point in time into milliseconds since the unix epoch. `None` is
+/// represented as 0
+fn timestamp_ms(val: Option<DateTime<Local>>) -> i64 {
+    val.map_or(0, |a| a.timestamp_millis())
+}
+
+/// Converts the time a response was received at from milliseconds since the
+/// unix epoch into the local time, that responses use. 0 is the current time.
+/// Returns `None`, if the timestamp is out of range
+fn received_at_from_ms(ms: i64) -> Option<NaiveDateTime> {
+    let time = match ms {
+        0 => Local::now(),
+        ms => DateTime::from_timestamp_millis(ms)?.with_timezone(&Local),
+    };
+    Some(time.naive_local())
+}
@@ -0,0 +1,94 @@
+use super::{ffi_guard, SfStr};
+use crate::session::PreparedRequest;
+
+/// Returns the full url, that the request has to be sent to via GET
+///
+/// # Safety
+/// `request` has to be null, or a valid request handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_request_url(
+    request: *const PreparedRequest,
+) -> SfStr {
+    ffi_guard("sf_request_url", || {
+        request
+            .as_ref()
+            .map_or_else(SfStr::missing, |r| r.url.as_str().into())
+    })
+}
+
+/// Returns the amount of headers, that have to be sent along with the request
+///
+/// # Safety
+/// `request` has to be null, or a valid request handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_request_header_count(
+    request: *const PreparedRequest,
+) -> usize {
+    ffi_guard("sf_request_header_count", || {
+        request.as_ref().map_or(0, |r| r.headers.len())
+    })
+}
+
+/// Returns the name of the header at the given index. Returns a missing
+/// string, if the index is out of bounds
+///
+/// # Safety
+/// `request` has to be null, or a valid request handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_request_header_name(
+    request: *const PreparedRequest,
+    idx: usize,
+) -> SfStr {
+    ffi_guard("sf_request_header_name", || {
+        request
+            .as_ref()
+            .and_then(|r| r.headers.get(idx))
+            .map_or_else(SfStr::missing, |h| h.0.as_str().into())
+    })
+}
+
+/// Returns the value of the header at the given index. Returns a missing
+/// string, if the index is out of bounds
+///
+/// # Safety
+/// `request` has to be null, or a valid request handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_request_header_value(
+    request: *const PreparedRequest,
+    idx: usize,
+) -> SfStr {
+    ffi_guard("sf_request_header_value", || {
+        request
+            .as_ref()
+            .and_then(|r| r.headers.get(idx))
+            .map_or_else(SfStr::missing, |h| h.1.as_str().into())
+    })
+}
+
+/// Returns the number of the request within its session. Requests have to be
+/// sent in the order of these numbers
+///
+/// # Safety
+/// `request` has to be null, or a valid request handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_request_sequence(
+    request: *const PreparedRequest,
+) -> u32 {
+    ffi_guard("sf_request_sequence", || {
+        request.as_ref().map_or(0, |r| r.sequence)
+    })
+}
+
+/// Frees a request
+///
+/// # Safety
+/// `request` has to be null, or a request handle, that has not yet been freed
+#[no_mangle]
+pub unsafe extern "C" fn sf_request_free(request: *mut PreparedRequest) {
+    ffi_guard("sf_request_free", || {
+        if request.is_null() {
+            return;
+        }
+        drop(Box::from_raw(request));
+    });
+}
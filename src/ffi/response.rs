@@ -0,0 +1,142 @@
+use std::{ffi::c_char, ptr};
+
+use chrono::Local;
+
+use super::{
+    c_str, ffi_guard, received_at_from_ms, set_invalid_argument, set_sf_error,
+    SfStr,
+};
+use crate::response::Response;
+
+/// Parses a raw response body, as the server would send it. This is useful to
+/// restore responses, that were stored earlier. `received_at` is the time the
+/// response was received at in milliseconds since the unix epoch, or 0 for the
+/// current time. Returns null, if the body is not a valid response
+///
+/// # Safety
+/// `body` has to be null, or a valid nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn sf_response_parse(
+    body: *const c_char,
+    received_at: i64,
+) -> *mut Response {
+    ffi_guard("sf_response_parse", || {
+        let (Some(body), Some(received_at)) =
+            (c_str(body), received_at_from_ms(received_at))
+        else {
+            set_invalid_argument("sf_response_parse");
+            return ptr::null_mut();
+        };
+        match Response::parse(body.to_string(), received_at) {
+            Ok(response) => Box::into_raw(Box::new(response)),
+            Err(e) => {
+                set_sf_error("sf_response_parse", &e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Returns the amount of keys in the response
+///
+/// # Safety
+/// `response` has to be null, or a valid response handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_response_key_count(
+    response: *const Response,
+) -> usize {
+    ffi_guard("sf_response_key_count", || {
+        response.as_ref().map_or(0, |r| r.values().len())
+    })
+}
+
+/// Returns the key at the given index. The order of keys is arbitrary, but
+/// stays the same for the lifetime of the response. Returns a missing string,
+/// if the index is out of bounds
+///
+/// # Safety
+/// `response` has to be null, or a valid response handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_response_key(
+    response: *const Response,
+    idx: usize,
+) -> SfStr {
+    ffi_guard("sf_response_key", || {
+        response
+            .as_ref()
+            .and_then(|r| r.values().keys().nth(idx).copied())
+            .map_or_else(SfStr::missing, SfStr::from)
+    })
+}
+
+/// Returns the raw value the server send for this key. Returns a missing
+/// string, if the response does not contain the key
+///
+/// # Safety
+/// `response` has to be null, or a valid response handle. `key` has to be
+/// null, or a valid nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn sf_response_value(
+    response: *const Response,
+    key: *const c_char,
+) -> SfStr {
+    ffi_guard("sf_response_value", || {
+        let (Some(response), Some(key)) = (response.as_ref(), c_str(key))
+        else {
+            return SfStr::missing();
+        };
+        response
+            .values()
+            .get(key)
+            .map_or_else(SfStr::missing, |v| v.as_str().into())
+    })
+}
+
+/// Returns the raw response body, that the server send
+///
+/// # Safety
+/// `response` has to be null, or a valid response handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_response_raw(response: *const Response) -> SfStr {
+    ffi_guard("sf_response_raw", || {
+        response
+            .as_ref()
+            .map_or_else(SfStr::missing, |r| r.raw_response().into())
+    })
+}
+
+/// Returns the time, at which the response was received, as milliseconds since
+/// the unix epoch. Returns 0 for a null response
+///
+/// # Safety
+/// `response` has to be null, or a valid response handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_response_received_at(
+    response: *const Response,
+) -> i64 {
+    ffi_guard("sf_response_received_at", || {
+        let Some(response) = response.as_ref() else {
+            return 0;
+        };
+        let received_at = response.received_at();
+        received_at.and_local_timezone(Local).latest().map_or_else(
+            || received_at.and_utc().timestamp_millis(),
+            |a| a.timestamp_millis(),
+        )
+    })
+}
+
+/// Frees a response
+///
+/// # Safety
+/// `response` has to be null, or a response handle, that has not yet been
+/// freed
+#[no_mangle]
+pub unsafe extern "C" fn sf_response_free(response: *mut Response) {
+    ffi_guard("sf_response_free", || {
+        if response.is_null() {
+            return;
+        }
+        drop(Box::from_raw(response));
+    });
+}
@@ -0,0 +1,137 @@
+use std::{ffi::c_void, future::Future, ptr};
+
+use tokio::runtime::{Builder, Runtime};
+
+use super::{
+    ffi_guard, panic_message, set_last_error, set_sf_error, SfErrorCode,
+};
+use crate::{error::SFError, session::Response};
+
+#[derive(Debug)]
+/// The runtime, that drives all network communication of the C ABI. This
+/// should be created once and shared by all sessions
+pub struct SfRuntime {
+    runtime: Runtime,
+}
+
+impl SfRuntime {
+    /// Blocks the current thread until the future has finished. This must not
+    /// be called from within a thread, that is driven by a runtime
+    pub(super) fn block_on<F: Future>(&self, future: F) -> F::Output {
+        self.runtime.block_on(future)
+    }
+
+    /// Runs the request in the background and returns immediately. Once the
+    /// request has finished, or panicked, the callback is called with the
+    /// result
+    pub(super) fn spawn_request<F>(
+        &self,
+        name: &'static str,
+        request: F,
+        callback: extern "C" fn(*mut c_void, *mut Response, SfErrorCode),
+        user_data: *mut c_void,
+    ) where
+        F: Future<Output = Result<Response, SFError>> + Send + 'static,
+    {
+        let user_data = SendPtr::new(user_data);
+        // The request runs in its own task, so that a panic only takes down
+        // that task and we can still notify the caller
+        let request = self.runtime.spawn(request);
+        self.runtime.spawn(async move {
+            let (response, code) = match request.await {
+                Ok(Ok(response)) => {
+                    (Box::into_raw(Box::new(response)), SfErrorCode::Ok)
+                }
+                Ok(Err(e)) => (ptr::null_mut(), set_sf_error(name, &e)),
+                Err(e) => {
+                    let msg = match e.try_into_panic() {
+                        Ok(payload) => {
+                            panic_message(payload.as_ref()).to_string()
+                        }
+                        Err(e) => e.to_string(),
+                    };
+                    set_last_error(
+                        SfErrorCode::Panic,
+                        &format!("{name}: {msg}"),
+                    );
+                    (ptr::null_mut(), SfErrorCode::Panic)
+                }
+            };
+            callback(user_data.get(), response, code);
+        });
+    }
+}
+
+/// Called, when a request has finished. If the request failed, `response` is
+/// null and `error` contains the reason. `sf_last_error_message` can be used
+/// within the callback to get more details. Otherwise the callee takes
+/// ownership of the response and has to free it with `sf_response_free`. This
+/// will be called from one of the runtime threads, not the thread, that
+/// started the request. A null callback is treated as an invalid argument
+pub type SfResponseCallback = Option<
+    extern "C" fn(
+        user_data: *mut c_void,
+        response: *mut Response,
+        error: SfErrorCode,
+    ),
+>;
+
+/// A pointer, that the caller of a `*_async` function has promised us to not
+/// touch until the callback has been called. This is what allows us to move it
+/// into another thread
+pub(super) struct SendPtr<T>(*mut T);
+
+// SAFETY: The caller guarantees, that the pointer is not used anywhere else
+// until the request has finished
+unsafe impl<T> Send for SendPtr<T> {
+}
+
+impl<T> SendPtr<T> {
+    pub(super) fn new(ptr: *mut T) -> Self {
+        Self(ptr)
+    }
+
+    pub(super) fn get(self) -> *mut T {
+        self.0
+    }
+}
+
+/// Creates a new multi threaded runtime. If `worker_threads` is 0, one thread
+/// per CPU core will be used. Returns null, if the runtime could not be created
+#[no_mangle]
+pub extern "C" fn sf_runtime_new(worker_threads: usize) -> *mut SfRuntime {
+    ffi_guard("sf_runtime_new", || {
+        let mut builder = Builder::new_multi_thread();
+        builder.enable_all();
+        if worker_threads > 0 {
+            builder.worker_threads(worker_threads);
+        }
+        match builder.build() {
+            Ok(runtime) => Box::into_raw(Box::new(SfRuntime { runtime })),
+            Err(e) => {
+                set_last_error(
+                    SfErrorCode::InvalidRequest,
+                    &format!("sf_runtime_new: Failed to create runtime: {e}"),
+                );
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Frees the runtime. Requests, that are still in flight, are cancelled and
+/// their callbacks will never be called
+///
+/// # Safety
+/// `runtime` has to be null, or a runtime handle, that has not yet been freed
+#[no_mangle]
+pub unsafe extern "C" fn sf_runtime_free(runtime: *mut SfRuntime) {
+    ffi_guard("sf_runtime_free", || {
+        if runtime.is_null() {
+            return;
+        }
+        // A normal drop would block until all tasks have stopped and panic, if
+        // this is called from within a callback
+        Box::from_raw(runtime).runtime.shutdown_background();
+    });
+}
@@ -0,0 +1,325 @@
+use std::{
+    ffi::{c_char, c_void},
+    ptr,
+};
+
+use super::{
+    c_str, ffi_guard, received_at_from_ms, set_invalid_argument,
+    set_last_error, set_sf_error, SendPtr, SfErrorCode, SfResponseCallback,
+    SfRuntime, SfStr,
+};
+use crate::{
+    command::Command,
+    session::{PreparedRequest, Response, ServerConnection, Session},
+};
+
+/// Creates a new session instance. Returns null, if any of the arguments is
+/// null, not valid UTF-8, or the server URL is invalid
+///
+/// # Safety
+/// All arguments have to be valid nul terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_new(
+    username: *const c_char,
+    password: *const c_char,
+    server_url: *const c_char,
+) -> *mut Session {
+    ffi_guard("sf_session_new", || {
+        let (Some(username), Some(password), Some(server_url)) =
+            (c_str(username), c_str(password), c_str(server_url))
+        else {
+            set_invalid_argument("sf_session_new");
+            return ptr::null_mut();
+        };
+
+        let Some(server_connection) = ServerConnection::new(server_url) else {
+            set_last_error(
+                SfErrorCode::InvalidArgument,
+                "sf_session_new: Invalid server url",
+            );
+            return ptr::null_mut();
+        };
+
+        let session = Session::new(username, password, server_connection);
+        Box::into_raw(Box::new(session))
+    })
+}
+
+/// Logs in using a session. Returns true, if the login was successful.
+/// Otherwise the reason can be read with `sf_last_error_code`. This
+/// blocks the calling thread until the server has responded and must not be
+/// called from one of the runtimes threads. Use `sf_session_login_async` for
+/// that
+///
+/// # Safety
+/// `runtime` and `session` have to be null, or valid handles
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_login(
+    runtime: *const SfRuntime,
+    session: *mut Session,
+) -> bool {
+    ffi_guard("sf_session_login", || {
+        let (Some(runtime), Some(session)) =
+            (runtime.as_ref(), session.as_mut())
+        else {
+            set_invalid_argument("sf_session_login");
+            return false;
+        };
+
+        match runtime.block_on(session.login()) {
+            Ok(_) => true,
+            Err(e) => {
+                set_sf_error("sf_session_login", &e);
+                false
+            }
+        }
+    })
+}
+
+/// Logs in using a session without blocking. The callback will be called with
+/// the login response, once the login has finished. Returns false and never
+/// calls the callback, if any of the arguments is invalid
+///
+/// # Safety
+/// `runtime` and `session` have to be null, or valid handles. The session must
+/// not be used, or freed until the callback has been called
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_login_async(
+    runtime: *const SfRuntime,
+    session: *mut Session,
+    callback: SfResponseCallback,
+    user_data: *mut c_void,
+) -> bool {
+    ffi_guard("sf_session_login_async", || {
+        let (Some(runtime), false, Some(callback)) =
+            (runtime.as_ref(), session.is_null(), callback)
+        else {
+            set_invalid_argument("sf_session_login_async");
+            return false;
+        };
+
+        let session = SendPtr::new(session);
+        runtime.spawn_request(
+            "sf_session_login_async",
+            async move { (*session.get()).login().await },
+            callback,
+            user_data,
+        );
+        true
+    })
+}
+
+/// Parses a `Command`, that was serialized as JSON
+///
+/// # Safety
+/// `command_json` has to be null, or a valid nul terminated string
+unsafe fn parse_command(
+    name: &str,
+    command_json: *const c_char,
+) -> Option<Command> {
+    let Some(command_json) = c_str(command_json) else {
+        set_invalid_argument(name);
+        return None;
+    };
+    match serde_json::from_str(command_json) {
+        Ok(command) => Some(command),
+        Err(e) => {
+            set_last_error(
+                SfErrorCode::InvalidArgument,
+                &format!("{name}: Invalid command: {e}"),
+            );
+            None
+        }
+    }
+}
+
+/// Sends a command to the server and returns the response. The command has to
+/// be a `Command` serialized as JSON, for example `"Update"`, or
+/// `{"StartQuest":{"quest_pos":0,"overwrite_inv":false}}`. Returns null, if the
+/// command could not be parsed, or sending it failed. The returned response
+/// has to be freed with `sf_response_free`. This blocks the calling thread
+/// until the server has responded and must not be called from one of the
+/// runtimes threads. Use `sf_session_send_command_async` for that
+///
+/// # Safety
+/// `runtime` and `session` have to be null, or valid handles. `command_json`
+/// has to be null, or a valid nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_send_command(
+    runtime: *const SfRuntime,
+    session: *mut Session,
+    command_json: *const c_char,
+) -> *mut Response {
+    ffi_guard("sf_session_send_command", || {
+        let (Some(runtime), Some(session)) =
+            (runtime.as_ref(), session.as_mut())
+        else {
+            set_invalid_argument("sf_session_send_command");
+            return ptr::null_mut();
+        };
+        let Some(command) =
+            parse_command("sf_session_send_command", command_json)
+        else {
+            return ptr::null_mut();
+        };
+
+        match runtime.block_on(session.send_command(command)) {
+            Ok(response) => Box::into_raw(Box::new(response)),
+            Err(e) => {
+                set_sf_error("sf_session_send_command", &e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Sends a command to the server without blocking. The command has the same
+/// format as in `sf_session_send_command`. The callback will be called with
+/// the response, once the server has answered. Returns false and never calls
+/// the callback, if any of the arguments is invalid
+///
+/// # Safety
+/// `runtime` and `session` have to be null, or valid handles. `command_json`
+/// has to be null, or a valid nul terminated string. The session must not be
+/// used, or freed until the callback has been called
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_send_command_async(
+    runtime: *const SfRuntime,
+    session: *mut Session,
+    command_json: *const c_char,
+    callback: SfResponseCallback,
+    user_data: *mut c_void,
+) -> bool {
+    ffi_guard("sf_session_send_command_async", || {
+        let (Some(runtime), false, Some(callback)) =
+            (runtime.as_ref(), session.is_null(), callback)
+        else {
+            set_invalid_argument("sf_session_send_command_async");
+            return false;
+        };
+        let Some(command) =
+            parse_command("sf_session_send_command_async", command_json)
+        else {
+            return false;
+        };
+
+        let session = SendPtr::new(session);
+        runtime.spawn_request(
+            "sf_session_send_command_async",
+            async move { (*session.get()).send_command(command).await },
+            callback,
+            user_data,
+        );
+        true
+    })
+}
+
+/// Encodes and encrypts the command into a request, without sending it. The
+/// command has the same format as in `sf_session_send_command`. The request
+/// can then be sent with any HTTP client and its response body has to be
+/// handed back to `sf_session_accept`. Returns null, if the command could not
+/// be encoded. The request has to be freed with `sf_request_free`
+///
+/// # Safety
+/// `session` has to be null, or a valid handle. `command_json` has to be null,
+/// or a valid nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_prepare(
+    session: *const Session,
+    command_json: *const c_char,
+) -> *mut PreparedRequest {
+    ffi_guard("sf_session_prepare", || {
+        let Some(session) = session.as_ref() else {
+            set_invalid_argument("sf_session_prepare");
+            return ptr::null_mut();
+        };
+        let Some(command) = parse_command("sf_session_prepare", command_json)
+        else {
+            return ptr::null_mut();
+        };
+        match session.prepare(&command) {
+            Ok(request) => Box::into_raw(Box::new(request)),
+            Err(e) => {
+                set_sf_error("sf_session_prepare", &e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Parses the response body, that the server sent for a request from
+/// `sf_session_prepare` and updates the session with it. `received_at` is the
+/// time the response was received at in milliseconds since the unix epoch, or
+/// 0 for the current time. Returns null, if the body is not a valid response,
+/// or contains an error. The response has to be freed with `sf_response_free`
+///
+/// # Safety
+/// `session` has to be null, or a valid handle. `body` has to be null, or a
+/// valid nul terminated string
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_accept(
+    session: *mut Session,
+    body: *const c_char,
+    received_at: i64,
+) -> *mut Response {
+    ffi_guard("sf_session_accept", || {
+        let (Some(session), Some(body), Some(received_at)) = (
+            session.as_mut(),
+            c_str(body),
+            received_at_from_ms(received_at),
+        ) else {
+            set_invalid_argument("sf_session_accept");
+            return ptr::null_mut();
+        };
+        match session.accept(body.to_string(), received_at) {
+            Ok(response) => Box::into_raw(Box::new(response)),
+            Err(e) => {
+                set_sf_error("sf_session_accept", &e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Returns the name of the character, that this session is responsible for
+///
+/// # Safety
+/// `session` has to be null, or a valid session handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_username(session: *const Session) -> SfStr {
+    ffi_guard("sf_session_username", || {
+        session
+            .as_ref()
+            .map_or_else(SfStr::missing, |s| s.username().into())
+    })
+}
+
+/// Returns the URL of the server, that this session is sending requests to
+///
+/// # Safety
+/// `session` has to be null, or a valid session handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_server_url(
+    session: *const Session,
+) -> SfStr {
+    ffi_guard("sf_session_server_url", || {
+        session
+            .as_ref()
+            .map_or_else(SfStr::missing, |s| s.server_url().as_str().into())
+    })
+}
+
+/// Frees a session instance
+///
+/// # Safety
+/// `session` has to be null, or a pointer returned by `sf_session_new`, that
+/// has not yet been freed
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_free(session: *mut Session) {
+    ffi_guard("sf_session_free", || {
+        if session.is_null() {
+            return;
+        }
+        drop(Box::from_raw(session));
+    });
+}
@@ -0,0 +1,83 @@
+use super::{
+    dungeon_from_id, ffi_guard, set_invalid_argument, set_last_error,
+    SfErrorCode,
+};
+use crate::{
+    gamestate::{dungeons::Dungeon, GameState},
+    simulate::{Battle, BattleFighter, BattleSide, PlayerFighterSquad},
+};
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+/// The aggregated outcome of a simulated fight
+pub struct SfSimulationResult {
+    /// The share of fights, that the player won, in the range 0.0..=1.0
+    pub win_rate: f64,
+    /// The average amount of rounds a fight lasted
+    pub avg_rounds: f64,
+}
+
+/// Simulates `iterations` fights of the character (and companions in shadow
+/// dungeons) against the next enemy of the dungeon. `typ` and `id` are the
+/// same as in `sf_dungeon_progress`. Returns false, if the arguments are
+/// invalid, or the dungeon has no enemy to fight (it is locked, or already
+/// finished)
+///
+/// # Safety
+/// `gs` has to be null, or a valid gamestate handle. `out` has to be null, or
+/// point to writable memory for one `SfSimulationResult`
+#[no_mangle]
+pub unsafe extern "C" fn sf_simulate_dungeon(
+    gs: *const GameState,
+    typ: u32,
+    id: u32,
+    iterations: u32,
+    out: *mut SfSimulationResult,
+) -> bool {
+    ffi_guard("sf_simulate_dungeon", || {
+        let (Some(gs), Some(dungeon), Some(out), true) = (
+            gs.as_ref(),
+            dungeon_from_id(typ, id),
+            out.as_mut(),
+            iterations > 0,
+        ) else {
+            set_invalid_argument("sf_simulate_dungeon");
+            return false;
+        };
+        let Some(monster) = gs.dungeons.current_enemy(dungeon) else {
+            set_last_error(
+                SfErrorCode::InvalidArgument,
+                "sf_simulate_dungeon: The dungeon has no enemy to fight",
+            );
+            return false;
+        };
+
+        let squad = PlayerFighterSquad::new(gs);
+        let mut player = match dungeon {
+            Dungeon::Light(_) => {
+                vec![BattleFighter::from_upgradeable(&squad.character)]
+            }
+            Dungeon::Shadow(_) => BattleFighter::from_squad(&squad),
+        };
+        let mut enemy = [BattleFighter::from_monster(monster)];
+        let mut battle = Battle::new(&mut player, &mut enemy);
+
+        let mut wins = 0u32;
+        let mut rounds = 0u64;
+        for _ in 0..iterations {
+            if battle.simulate(&mut ()) == BattleSide::Left {
+                wins += 1;
+            }
+            rounds += u64::from(battle.round);
+        }
+
+        #[allow(clippy::cast_precision_loss)]
+        {
+            *out = SfSimulationResult {
+                win_rate: f64::from(wins) / f64::from(iterations),
+                avg_rounds: rounds as f64 / f64::from(iterations),
+            };
+        }
+        true
+    })
+}
@@ -0,0 +1,266 @@
+use std::{ffi::c_char, ptr};
+
+use super::{
+    c_str, ffi_guard, set_invalid_argument, set_sf_error, SfRuntime, SfStr,
+};
+use crate::{
+    error::SFError,
+    session::{PWHash, Session},
+    sso::SFAccount,
+};
+
+/// Logs in to a S&F SSO account with its clear text credentials. This blocks
+/// the calling thread until the server has responded. Returns null, if the
+/// login failed. The account has to be freed with `sf_account_free`, or
+/// consumed by `sf_account_characters`
+///
+/// # Safety
+/// `runtime` has to be null, or a valid handle. `username` and `password` have
+/// to be null, or valid nul terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn sf_account_login(
+    runtime: *const SfRuntime,
+    username: *const c_char,
+    password: *const c_char,
+) -> *mut SFAccount {
+    ffi_guard("sf_account_login", || {
+        let (Some(runtime), Some(username), Some(password)) =
+            (runtime.as_ref(), c_str(username), c_str(password))
+        else {
+            set_invalid_argument("sf_account_login");
+            return ptr::null_mut();
+        };
+        let login =
+            SFAccount::login(username.to_string(), password.to_string());
+        match runtime.block_on(login) {
+            Ok(account) => Box::into_raw(Box::new(account)),
+            Err(e) => {
+                set_sf_error("sf_account_login", &e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Logs in to a S&F SSO account with the hash of its password. Apart from
+/// that, this behaves exactly like `sf_account_login`
+///
+/// # Safety
+/// `runtime` has to be null, or a valid handle. `username` and `pw_hash` have
+/// to be null, or valid nul terminated strings
+#[no_mangle]
+pub unsafe extern "C" fn sf_account_login_hashed(
+    runtime: *const SfRuntime,
+    username: *const c_char,
+    pw_hash: *const c_char,
+) -> *mut SFAccount {
+    ffi_guard("sf_account_login_hashed", || {
+        let (Some(runtime), Some(username), Some(pw_hash)) =
+            (runtime.as_ref(), c_str(username), c_str(pw_hash))
+        else {
+            set_invalid_argument("sf_account_login_hashed");
+            return ptr::null_mut();
+        };
+        let login = SFAccount::login_hashed(
+            username.to_string(),
+            PWHash::from_hash(pw_hash.to_string()),
+        );
+        match runtime.block_on(login) {
+            Ok(account) => Box::into_raw(Box::new(account)),
+            Err(e) => {
+                set_sf_error("sf_account_login_hashed", &e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Returns the username of the account
+///
+/// # Safety
+/// `account` has to be null, or a valid account handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_account_username(
+    account: *const SFAccount,
+) -> SfStr {
+    ffi_guard("sf_account_username", || {
+        account
+            .as_ref()
+            .map_or_else(SfStr::missing, |a| a.username().into())
+    })
+}
+
+/// Frees an account, that was not consumed by `sf_account_characters`
+///
+/// # Safety
+/// `account` has to be null, or an account handle, that has not yet been freed,
+/// or consumed
+#[no_mangle]
+pub unsafe extern "C" fn sf_account_free(account: *mut SFAccount) {
+    ffi_guard("sf_account_free", || {
+        if account.is_null() {
+            return;
+        }
+        drop(Box::from_raw(account));
+    });
+}
+
+#[derive(Debug)]
+/// The sessions of all characters on a SSO account
+pub struct SfSessionList {
+    sessions: Vec<Result<Session, SFError>>,
+}
+
+/// Fetches all characters of the account and returns a session for each of
+/// them. This always consumes the account, even if the request fails, so it
+/// must not be used, or freed afterwards. This blocks the calling thread until
+/// the server has responded. Returns null, if the characters could not be
+/// fetched. The list has to be freed with `sf_session_list_free`
+///
+/// # Safety
+/// `runtime` has to be null, or a valid handle. `account` has to be null, or
+/// an account handle, that has not yet been freed, or consumed
+#[no_mangle]
+pub unsafe extern "C" fn sf_account_characters(
+    runtime: *const SfRuntime,
+    account: *mut SFAccount,
+) -> *mut SfSessionList {
+    ffi_guard("sf_account_characters", || {
+        if account.is_null() {
+            set_invalid_argument("sf_account_characters");
+            return ptr::null_mut();
+        }
+        let account = *Box::from_raw(account);
+        let Some(runtime) = runtime.as_ref() else {
+            set_invalid_argument("sf_account_characters");
+            return ptr::null_mut();
+        };
+        match runtime.block_on(account.characters()) {
+            Ok(sessions) => Box::into_raw(Box::new(SfSessionList { sessions })),
+            Err(e) => {
+                set_sf_error("sf_account_characters", &e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Returns the amount of sessions in the list. This does not change, when
+/// sessions are taken out of the list
+///
+/// # Safety
+/// `list` has to be null, or a valid session list handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_list_len(
+    list: *const SfSessionList,
+) -> usize {
+    ffi_guard("sf_session_list_len", || {
+        list.as_ref().map_or(0, |l| l.sessions.len())
+    })
+}
+
+/// Returns the session at the given index, without removing it from the list.
+/// The session is owned by the list and must not be freed. Returns null, if the
+/// index is out of bounds, the session was already taken, or the server of the
+/// character could not be determined (see `sf_last_error_code`)
+///
+/// # Safety
+/// `list` has to be null, or a valid session list handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_list_get(
+    list: *const SfSessionList,
+    idx: usize,
+) -> *const Session {
+    ffi_guard("sf_session_list_get", || {
+        let Some(session) = list.as_ref().and_then(|l| l.sessions.get(idx))
+        else {
+            set_invalid_argument("sf_session_list_get");
+            return ptr::null();
+        };
+        match session {
+            Ok(session) => ptr::from_ref(session),
+            Err(e) => {
+                set_sf_error("sf_session_list_get", e);
+                ptr::null()
+            }
+        }
+    })
+}
+
+/// Removes the session at the given index from the list and hands ownership
+/// of it to the caller. It has to be freed with `sf_session_free`. Returns null
+/// in the same cases as `sf_session_list_get`
+///
+/// # Safety
+/// `list` has to be null, or a valid session list handle
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_list_take(
+    list: *mut SfSessionList,
+    idx: usize,
+) -> *mut Session {
+    ffi_guard("sf_session_list_take", || {
+        let Some(session) = list.as_mut().and_then(|l| l.sessions.get_mut(idx))
+        else {
+            set_invalid_argument("sf_session_list_take");
+            return ptr::null_mut();
+        };
+        let taken = std::mem::replace(
+            session,
+            Err(SFError::InvalidRequest("The session was already taken")),
+        );
+        match taken {
+            Ok(session) => Box::into_raw(Box::new(session)),
+            Err(e) => {
+                set_sf_error("sf_session_list_take", &e);
+                // Keep the original error around for later calls
+                *session = Err(e);
+                ptr::null_mut()
+            }
+        }
+    })
+}
+
+/// Frees the list and all sessions, that have not been taken out of it
+///
+/// # Safety
+/// `list` has to be null, or a session list handle, that has not yet been
+/// freed
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_list_free(list: *mut SfSessionList) {
+    ffi_guard("sf_session_list_free", || {
+        if list.is_null() {
+            return;
+        }
+        drop(Box::from_raw(list));
+    });
+}
+
+/// Retrieves new SSO credentials for the session from its account. This should
+/// be called, when the server reports, that the session has expired.
+/// Afterwards the session has to log in again. This blocks the calling thread
+/// until the server has responded. Returns false, if the credentials could not
+/// be renewed, or the session does not belong to a SSO account
+///
+/// # Safety
+/// `runtime` and `session` have to be null, or valid handles
+#[no_mangle]
+pub unsafe extern "C" fn sf_session_renew_sso_creds(
+    runtime: *const SfRuntime,
+    session: *mut Session,
+) -> bool {
+    ffi_guard("sf_session_renew_sso_creds", || {
+        let (Some(runtime), Some(session)) =
+            (runtime.as_ref(), session.as_mut())
+        else {
+            set_invalid_argument("sf_session_renew_sso_creds");
+            return false;
+        };
+        match runtime.block_on(session.renew_sso_creds()) {
+            Ok(()) => true,
+            Err(e) => {
+                set_sf_error("sf_session_renew_sso_creds", &e);
+                false
+            }
+        }
+    })
+}
@@ -299,7 +299,7 @@ impl Dungeons {
                     }
                 }
             }
-        };
+        }
     }
 }
 
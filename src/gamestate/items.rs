@@ -412,7 +412,7 @@ impl Item {
             }
         }
         let model_id: u16 =
-            data.cimget(1, "item model id", |x| ((x & 0xFFFF) % 1000))?;
+            data.cimget(1, "item model id", |x| (x & 0xFFFF) % 1000)?;
 
         let color = match model_id {
             ..=49 if typ != ItemType::Talisman => data
@@ -237,7 +237,11 @@ impl GameState {
                     let raw_skip = val.into::<i32>("skip allow")?;
                     self.tavern.mushroom_skip_allowed = raw_skip != 0;
                 }
-                "cryptoid not found" => return Err(SFError::ConnectionError),
+                SESSION_INVALID => {
+                    return Err(SFError::ServerError(
+                        SESSION_INVALID.to_string(),
+                    ))
+                }
                 "ownplayersave" => {
                     self.update_player_save(&val.into_list("player save")?)?;
                 }
@@ -717,7 +721,7 @@ impl GameState {
                              {}",
                             data.len()
                         );
-                    };
+                    }
                     self.tavern.expeditions.available = data
                         .chunks_exact(8)
                         .map(|data| {
@@ -735,7 +739,7 @@ impl GameState {
                                     .unwrap_or_default(),
                             })
                         })
-                        .collect::<Result<_, _>>()?;
+                        .collect::<Result<_, SFError>>()?;
                 }
                 "expeditionrewardresources" => {
                     // I would assume, that everything we get is just update
@@ -883,7 +887,7 @@ impl GameState {
                         match InboxEntry::parse(msg, server_time) {
                             Ok(msg) => self.mail.inbox.push(msg),
                             Err(e) => warn!("Invalid msg: {msg} {e}"),
-                        };
+                        }
                     }
                 }
                 "messagetext" => {
@@ -131,7 +131,7 @@ impl Tavern {
     /// the `questing_preference`, they will be shown. Otherwise you will get
     /// quests
     #[must_use]
-    pub fn available_tasks(&self) -> AvailableTasks {
+    pub fn available_tasks(&self) -> AvailableTasks<'_> {
         if self.questing_preference == ExpeditionSetting::PreferExpeditions
             && self.expeditions.is_event_ongoing()
         {
@@ -52,7 +52,7 @@ impl HellevatorEvent {
     /// Information about it. Note that you still need to check the level >= 10
     /// requirement yourself
     #[must_use]
-    pub fn status(&self) -> HellevatorStatus {
+    pub fn status(&self) -> HellevatorStatus<'_> {
         match self.active.as_ref() {
             None => HellevatorStatus::NotAvailable,
             Some(h) if !self.is_event_ongoing() => {
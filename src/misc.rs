@@ -1,6 +1,7 @@
 use std::{
-    fmt::{Debug, Display},
+    fmt::{Debug, Display, Write},
     str::FromStr,
+    sync::LazyLock,
 };
 
 use aho_corasick::AhoCorasick;
@@ -9,7 +10,6 @@ use chrono::{DateTime, Local};
 use enum_map::{Enum, EnumArray, EnumMap};
 use log::{error, warn};
 use num_traits::FromPrimitive;
-use once_cell::sync::Lazy;
 
 use crate::{error::SFError, gamestate::ServerTime};
 
@@ -27,7 +27,7 @@ pub fn sha1_hash(val: &str) -> String {
     let hash = hasher.finalize();
     let mut result = String::with_capacity(hash.len() * 2);
     for byte in &hash {
-        result.push_str(&format!("{byte:02x}"));
+        _ = write!(result, "{byte:02x}");
     }
     result
 }
@@ -108,8 +108,8 @@ pub fn to_sf_string(val: &str) -> String {
 /// probably worth it
 #[allow(clippy::expect_used)]
 fn pattern_replace<const FROM: bool>(str: &str) -> String {
-    static A: Lazy<(AhoCorasick, &'static [&'static str; 11])> =
-        Lazy::new(|| {
+    static A: LazyLock<(AhoCorasick, &'static [&'static str; 11])> =
+        LazyLock::new(|| {
             let l = sf_str_lookups();
             (
                 aho_corasick::AhoCorasick::new(l.0)
@@ -118,8 +118,8 @@ fn pattern_replace<const FROM: bool>(str: &str) -> String {
             )
         });
 
-    static B: Lazy<(AhoCorasick, &'static [&'static str; 11])> =
-        Lazy::new(|| {
+    static B: LazyLock<(AhoCorasick, &'static [&'static str; 11])> =
+        LazyLock::new(|| {
             let l = sf_str_lookups();
             (
                 aho_corasick::AhoCorasick::new(l.1)
@@ -229,7 +229,7 @@ pub fn decrypt_server_request(
         .map_err(|_| SFError::InvalidRequest("Decrypted value is not UTF8"))
 }
 
-#[cfg(feature = "session")]
+#[cfg(feature = "protocol")]
 pub(crate) fn encrypt_server_request(
     to_encrypt: String,
     key: &str,
@@ -0,0 +1,377 @@
+//! A local imitation of the S&F game server. It speaks the same encrypted
+//! protocol as the real one, so a normal `Session` can log in, poll and send
+//! commands to it, which allows the session logic to be tested without any
+//! network access. Responses to commands come from fixtures, that are usually
+//! copied from real server responses
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    net::SocketAddr,
+    path::Path,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use log::{debug, warn};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+};
+
+use crate::{
+    misc::{
+        decrypt_server_request, sha1_hash, DEFAULT_CRYPTO_ID,
+        DEFAULT_CRYPTO_KEY, DEFAULT_SESSION_ID,
+    },
+    session::{
+        ConnectionOptions, PWHash, PreparedRequest, Transport, TransportFuture,
+        TransportResponse,
+    },
+};
+
+#[derive(Debug, Clone)]
+/// Decides how the mock server answers requests
+pub struct MockServerConfig {
+    /// The version, that the server reports in its responses
+    pub server_version: u32,
+    /// The characters, that are allowed to log in, by name. If this is empty,
+    /// any login is accepted
+    pub accounts: HashMap<String, PWHash>,
+    /// The responses to commands, by the name of the command (e.g.
+    /// `PlayerAdventureStart`). The responses to `AccountLogin` and `Poll`
+    /// are appended to the values, that the server always sends for these
+    pub responses: HashMap<String, String>,
+}
+
+impl Default for MockServerConfig {
+    fn default() -> Self {
+        Self {
+            server_version: ConnectionOptions::default()
+                .expected_server_version,
+            accounts: HashMap::new(),
+            responses: HashMap::new(),
+        }
+    }
+}
+
+impl MockServerConfig {
+    /// Adds a character, that can log in with the given password
+    #[must_use]
+    pub fn with_account(mut self, username: &str, password: &str) -> Self {
+        self.accounts
+            .insert(username.to_string(), PWHash::new(password));
+        self
+    }
+
+    /// Sets the response to the command with the given name
+    #[must_use]
+    pub fn with_response(mut self, command: &str, response: &str) -> Self {
+        self.responses
+            .insert(command.to_string(), response.to_string());
+        self
+    }
+
+    /// Reads all `<command name>.txt` files in the directory as the responses
+    /// to the respective commands
+    ///
+    /// # Errors
+    /// Returns an error, if the directory, or one of the files could not be
+    /// read
+    pub fn load_fixtures(&mut self, dir: impl AsRef<Path>) -> io::Result<()> {
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|a| a != "txt") {
+                continue;
+            }
+            let Some(name) = path.file_stem().and_then(|a| a.to_str()) else {
+                continue;
+            };
+            let response = std::fs::read_to_string(&path)?;
+            self.responses
+                .insert(name.to_string(), response.trim().to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// An error, that the mock server sends instead of the next regular response
+pub enum MockFailure {
+    /// Responds with the given HTTP status code and an empty body
+    Status(u16),
+    /// Responds with an empty body, like the server does for unknown sessions
+    Empty,
+    /// Responds with an ingame error with the given message
+    Error(String),
+    /// Forgets the session, that sent the request and tells it, that it has
+    /// expired. Following requests of that session fail in the same way,
+    /// until it logs in again. Just like the real server, this is also what a
+    /// session receives, after somebody else has logged in to its character
+    SessionExpired,
+}
+
+#[derive(Debug)]
+struct MockSession {
+    username: String,
+    session_id: String,
+    crypto_key: String,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    /// All sessions, that are currently logged in, by their crypto id
+    sessions: HashMap<String, MockSession>,
+    failures: VecDeque<MockFailure>,
+}
+
+#[derive(Debug, Clone, Default)]
+/// A fake game server. Cloning this gives you another handle to the same
+/// server. It can either be run as a real HTTP server with `serve()`, or be
+/// used directly as the `Transport` of a `ServerConnection`
+pub struct MockServer {
+    config: Arc<MockServerConfig>,
+    state: Arc<Mutex<MockState>>,
+}
+
+/// What the server responds with, when it does not know the crypto id
+const SESSION_EXPIRED: &str = "cryptoid not found:";
+
+fn random_string(len: usize) -> String {
+    std::iter::repeat_with(fastrand::alphanumeric)
+        .take(len)
+        .collect()
+}
+
+impl MockServer {
+    /// Creates a new server, that answers requests based on the config
+    #[must_use]
+    pub fn new(config: MockServerConfig) -> Self {
+        Self {
+            config: Arc::new(config),
+            state: Arc::default(),
+        }
+    }
+
+    /// Makes the server respond to one of the next requests with the failure
+    /// instead of the normal response. Failures are used up in the order they
+    /// were added
+    pub fn fail_next(&self, failure: MockFailure) {
+        self.state().failures.push_back(failure);
+    }
+
+    /// Forgets all sessions, as if the server had been restarted
+    pub fn expire_sessions(&self) {
+        self.state().sessions.clear();
+    }
+
+    /// The amount of sessions, that are currently logged in
+    #[must_use]
+    pub fn session_count(&self) -> usize {
+        self.state().sessions.len()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Answers a request to the given path (including the query). Returns the
+    /// HTTP status and the body of the response
+    #[must_use]
+    pub fn handle(&self, path: &str) -> (u16, String) {
+        let Some(path) = path.strip_prefix('/') else {
+            return (404, String::new());
+        };
+        let Some(query) = path.strip_prefix("req.php?") else {
+            return (404, String::new());
+        };
+        let Some(req) = url::form_urlencoded::parse(query.as_bytes())
+            .find(|a| a.0 == "req")
+            .map(|a| a.1.into_owned())
+        else {
+            return (400, String::new());
+        };
+        let (Some(crypto_id), Some(encrypted)) = (
+            req.get(..DEFAULT_CRYPTO_ID.len()),
+            req.get(DEFAULT_CRYPTO_ID.len()..),
+        ) else {
+            return (400, String::new());
+        };
+
+        let mut state = self.state();
+        let crypto_key = if crypto_id == DEFAULT_CRYPTO_ID {
+            DEFAULT_CRYPTO_KEY
+        } else if let Some(session) = state.sessions.get(crypto_id) {
+            session.crypto_key.as_str()
+        } else {
+            return (200, SESSION_EXPIRED.to_string());
+        };
+        let Ok(decrypted) = decrypt_server_request(encrypted, crypto_key)
+        else {
+            return (400, String::new());
+        };
+        let Some((session_id, command)) =
+            decrypted.trim_end_matches('|').split_once('|')
+        else {
+            return (400, String::new());
+        };
+        let (name, args) = command.split_once(':').unwrap_or((command, ""));
+        debug!("Mock server received {name}:{args}");
+
+        if let Some(failure) = state.failures.pop_front() {
+            return match failure {
+                MockFailure::Status(status) => (status, String::new()),
+                MockFailure::Empty => (200, String::new()),
+                MockFailure::Error(msg) => (200, format!("Error:{msg}")),
+                MockFailure::SessionExpired => {
+                    state.sessions.remove(crypto_id);
+                    (200, SESSION_EXPIRED.to_string())
+                }
+            };
+        }
+
+        if name == "AccountLogin" {
+            return (200, self.login(&mut state, args));
+        }
+
+        let logged_in = state
+            .sessions
+            .get(crypto_id)
+            .is_some_and(|a| a.session_id == session_id);
+        if !logged_in {
+            return (200, SESSION_EXPIRED.to_string());
+        }
+
+        let version = self.config.server_version;
+        let body = match (name, self.config.responses.get(name)) {
+            ("Poll", Some(resp)) => format!("serverversion:{version}&{resp}"),
+            ("Poll", None) => format!("serverversion:{version}&Success:"),
+            (_, Some(resp)) => resp.clone(),
+            (_, None) => {
+                warn!("Mock server has no response for {name}");
+                "Error:command not supported".to_string()
+            }
+        };
+        (200, body)
+    }
+
+    fn login(&self, state: &mut MockState, args: &str) -> String {
+        let mut args = args.split('/');
+        let (Some(username), Some(full_hash), Some(login_count)) =
+            (args.next(), args.next(), args.next())
+        else {
+            return "Error:invalid login".to_string();
+        };
+        if !self.config.accounts.is_empty() {
+            let valid = self.config.accounts.get(username).is_some_and(|a| {
+                sha1_hash(&format!("{}{login_count}", a.get())) == full_hash
+            });
+            if !valid {
+                return "Error:wrong pass".to_string();
+            }
+        }
+
+        // Just like the real server, only the latest login of a character is
+        // valid
+        state.sessions.retain(|_, a| a.username != username);
+
+        let session_id = random_string(DEFAULT_SESSION_ID.len());
+        let crypto_id = format!("0-{}", random_string(14));
+        let crypto_key = random_string(16);
+        let resp = format!(
+            "serverversion:{}&preregister:0&sessionid:{session_id}&cryptoid:\
+             {crypto_id}&cryptokey:{crypto_key}&{}",
+            self.config.server_version,
+            self.config
+                .responses
+                .get("AccountLogin")
+                .map_or("", |a| a.as_str())
+        );
+        state.sessions.insert(
+            crypto_id,
+            MockSession {
+                username: username.to_string(),
+                session_id,
+                crypto_key,
+            },
+        );
+        resp
+    }
+
+    /// Binds to the address and answers requests in the background. Returns
+    /// the address, that the server is listening on, which is useful, if the
+    /// port was 0. This has to be called from within a tokio runtime
+    ///
+    /// # Errors
+    /// Returns an error, if the address could not be bound
+    pub async fn spawn(
+        &self,
+        addr: impl ToSocketAddrs,
+    ) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let server = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = server.serve(listener).await {
+                warn!("Mock server stopped: {e}");
+            }
+        });
+        Ok(local_addr)
+    }
+
+    /// Answers HTTP requests on the listener until accepting a connection
+    /// fails
+    ///
+    /// # Errors
+    /// Returns the error, that made accepting a new connection fail
+    pub async fn serve(&self, listener: TcpListener) -> io::Result<()> {
+        loop {
+            let (stream, _) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_connection(stream).await {
+                    debug!("Mock server connection failed: {e}");
+                }
+            });
+        }
+    }
+
+    async fn handle_connection(&self, mut stream: TcpStream) -> io::Result<()> {
+        let mut buf = Vec::new();
+        let mut chunk = [0; 4096];
+        // We only care about the request line, so we just read until the end
+        // of the headers. Requests to the server never have a body
+        while !buf.windows(4).any(|a| a == b"\r\n\r\n") {
+            let read = stream.read(&mut chunk).await?;
+            if read == 0 {
+                return Ok(());
+            }
+            buf.extend_from_slice(chunk.get(..read).unwrap_or_default());
+        }
+        let request = String::from_utf8_lossy(&buf);
+        let mut request_line =
+            request.lines().next().unwrap_or_default().split(' ');
+        let (status, body) = match (request_line.next(), request_line.next()) {
+            (Some("GET"), Some(path)) => self.handle(path),
+            _ => (405, String::new()),
+        };
+        let response = format!(
+            "HTTP/1.1 {status} {}\r\nContent-Type: \
+             text/plain\r\nContent-Length: {}\r\nConnection: \
+             close\r\n\r\n{body}",
+            if status == 200 { "OK" } else { "Error" },
+            body.len()
+        );
+        stream.write_all(response.as_bytes()).await?;
+        stream.shutdown().await
+    }
+}
+
+impl Transport for MockServer {
+    fn send<'a>(&'a self, request: &'a PreparedRequest) -> TransportFuture<'a> {
+        let path = match request.url.query() {
+            Some(query) => format!("{}?{query}", request.url.path()),
+            None => request.url.path().to_string(),
+        };
+        let (status, body) = self.handle(&path);
+        Box::pin(async move { Ok(TransportResponse::new(status, body)) })
+    }
+}
@@ -0,0 +1,327 @@
+//! The IO free part of the communication with the server. `SessionState`
+//! encodes and encrypts commands into requests and keeps track of the session
+//! id, crypto settings and sequence numbers, that the responses hand out. It
+//! never sends anything on its own and does not depend on `reqwest`, or
+//! `tokio`, so it can be used with any HTTP stack, including the ones in the
+//! browser (WASM). `Session` is built on top of this and adds the actual
+//! sending, retries, rate limits, etc.
+use std::{
+    sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use chrono::NaiveDateTime;
+use log::{trace, warn};
+use url::Url;
+
+use crate::{
+    command::Command,
+    error::{SFError, SESSION_INVALID},
+    misc::{
+        encrypt_server_request, sha1_hash, DEFAULT_CRYPTO_ID,
+        DEFAULT_CRYPTO_KEY, DEFAULT_SESSION_ID, HASH_CONST,
+    },
+    response::Response,
+};
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+/// The password of a character, hashed in the way, that the server expects
+pub struct PWHash(String);
+
+impl PWHash {
+    /// Hashes the password the way the server expects it. You can use this to
+    /// store user passwords safely (not in cleartext)
+    #[must_use]
+    pub fn new(password: &str) -> Self {
+        Self(sha1_hash(&(password.to_string() + HASH_CONST)))
+    }
+    /// If you have access to the hash of the password directly, this method
+    /// lets you construct a `PWHash` directly
+    #[must_use]
+    pub fn from_hash(hash: String) -> Self {
+        Self(hash)
+    }
+
+    /// Gives you the hash of the password directly
+    #[must_use]
+    pub fn get(&self) -> &str {
+        &self.0
+    }
+}
+
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+/// A request to the game server, that has already been fully encoded and
+/// encrypted by `SessionState::prepare()`. All that has to be done is to send
+/// a GET request to `url` with the given headers
+pub struct PreparedRequest {
+    /// The full url of the request, including the encrypted command
+    pub url: Url,
+    /// The unencrypted command, that is contained in `url`. This is only
+    /// informational and does not have to be sent anywhere
+    pub command: String,
+    /// Additional headers, that have to be sent along with the request, as
+    /// (name, value) pairs
+    pub headers: Vec<(String, String)>,
+    /// The number of this request within the session. The server expects
+    /// requests to arrive in this order
+    pub sequence: u32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// The parts of the `ConnectionOptions`, that change the content of requests,
+/// or how responses are checked
+pub struct ProtocolOptions {
+    /// The version of the game client, that we claim to be, when logging in
+    pub app_version: String,
+    /// The platform of the game client, that we claim to be, when logging in
+    pub platform: String,
+    /// The language code (e.g. `en`, or `de`), that is sent to the server for
+    /// logins, registrations and in the `Accept-Language` header of every
+    /// request
+    pub language: String,
+    /// The server version, that this API was last tested on. Responses from
+    /// newer servers are logged, or rejected (see
+    /// `error_on_unsupported_version`)
+    pub expected_server_version: u32,
+    /// If this is true, every response from a server with a version greater,
+    /// than `expected_server_version` is rejected
+    pub error_on_unsupported_version: bool,
+    /// The server rejects sessions with the same error, regardless of whether
+    /// they have expired, or somebody else has logged in to the character.
+    /// If this is set, a rejection within this time after the last response
+    /// is assumed to be caused by another login and results in
+    /// `SessionTakenOver`. Otherwise it is treated as an expired session
+    pub takeover_window: Option<Duration>,
+}
+
+impl Default for ProtocolOptions {
+    fn default() -> Self {
+        Self {
+            app_version: "2100000000000".to_string(),
+            platform: "unity3d_webglplayer".to_string(),
+            language: "en".to_string(),
+            expected_server_version: 2005,
+            error_on_unsupported_version: false,
+            takeover_window: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+/// The login and crypto state of one character on a server. Requests are
+/// created with `prepare()` and the bodies of their responses have to be
+/// handed back to `accept()` in the same order. Clones share their sequence
+/// number, so requests from all clones have to be sent in the order they were
+/// prepared
+pub struct SessionState {
+    pub(crate) server_url: Url,
+    /// The id of our session. This will remain the same as long as our login
+    /// is valid and nobody else logs in
+    pub(crate) session_id: String,
+    /// The amount of commands we have send
+    pub(crate) command_count: Arc<AtomicU32>,
+    pub(crate) login_count: u32,
+    pub(crate) crypto_id: String,
+    pub(crate) crypto_key: String,
+    /// The time at which the last response of the server was received
+    pub(crate) last_response: Option<NaiveDateTime>,
+    pub(crate) options: ProtocolOptions,
+}
+
+impl SessionState {
+    /// Creates the state of a session, that is not logged in yet, on the
+    /// server with the given url (e.g. `https://s1.sfgame.net/`)
+    #[must_use]
+    pub fn new(server_url: Url, options: ProtocolOptions) -> Self {
+        Self {
+            server_url,
+            session_id: DEFAULT_SESSION_ID.to_string(),
+            command_count: Arc::new(AtomicU32::new(0)),
+            login_count: 1,
+            crypto_id: DEFAULT_CRYPTO_ID.to_string(),
+            crypto_key: DEFAULT_CRYPTO_KEY.to_string(),
+            last_response: None,
+            options,
+        }
+    }
+
+    /// The server, that the requests are sent to
+    #[must_use]
+    pub fn server_url(&self) -> &Url {
+        &self.server_url
+    }
+
+    /// The login count, that the next `Command::Login` has to contain
+    #[must_use]
+    pub fn login_count(&self) -> u32 {
+        self.login_count
+    }
+
+    /// Checks if the server has ever given this session a session id, which
+    /// it only does after a successful login
+    #[must_use]
+    pub fn has_session_id(&self) -> bool {
+        self.session_id.chars().any(|a| a != '0')
+    }
+
+    /// Resets all values related to the server connection back to the "not
+    /// logged in" state. This is basically the equivalent of clearing
+    /// browserdata, to logout
+    pub fn logout(&mut self) {
+        self.crypto_key = DEFAULT_CRYPTO_KEY.to_string();
+        self.crypto_id = DEFAULT_CRYPTO_ID.to_string();
+        self.login_count = 1;
+        self.command_count = Arc::new(AtomicU32::new(0));
+        self.session_id = DEFAULT_SESSION_ID.to_string();
+        self.last_response = None;
+    }
+
+    /// Checks if the server rejecting this session at the given time means,
+    /// that somebody else has logged in to the character (see
+    /// `ProtocolOptions::takeover_window`)
+    fn is_taken_over(&self, rejected_at: NaiveDateTime) -> bool {
+        let (Some(window), Some(last)) =
+            (self.options.takeover_window, self.last_response)
+        else {
+            return false;
+        };
+        self.has_session_id()
+            && rejected_at
+                .signed_duration_since(last)
+                .to_std()
+                .is_ok_and(|a| a < window)
+    }
+
+    /// Encodes and encrypts the command into a request, that can be sent to
+    /// the server with any HTTP client. Every call consumes a new sequence
+    /// number, so requests should be sent in the order they were prepared. The
+    /// response has to be handed back to `accept()`
+    ///
+    /// # Errors
+    /// - `InvalidRequest`: If the command could not be encoded into a valid
+    ///   request
+    pub fn prepare(
+        &self,
+        command: &Command,
+    ) -> Result<PreparedRequest, SFError> {
+        trace!("Sending a {command:?} command");
+
+        let plain_command = command.request_string(&self.options)?;
+        let mut command_str = format!("{}|{plain_command}", self.session_id);
+
+        while command_str.len() % 16 > 0 {
+            command_str.push('|');
+        }
+
+        trace!("Command string: {command_str}");
+        let sequence = self.command_count.fetch_add(1, Ordering::SeqCst);
+        let url = format!(
+            "{}req.php?req={}{}&rnd={:.7}&c={}",
+            self.server_url,
+            &self.crypto_id,
+            encrypt_server_request(command_str, &self.crypto_key)?,
+            fastrand::f64(), // Pretty sure this is just cache busting
+            sequence
+        );
+        trace!("Full request url: {url}");
+
+        // Make sure we dont have any weird stuff in our URL
+        let url = Url::parse(&url).map_err(|_| {
+            SFError::InvalidRequest("Could not parse command url")
+        })?;
+
+        Ok(PreparedRequest {
+            url,
+            command: plain_command,
+            headers: vec![
+                ("referer".to_string(), self.server_url.to_string()),
+                (
+                    "accept-language".to_string(),
+                    format!("{};q=0.7,en-US;q=0.6", self.options.language),
+                ),
+            ],
+            sequence,
+        })
+    }
+
+    /// Parses the body of a response, that the server sent for a request from
+    /// `prepare()` and checks the server version, without updating this state
+    ///
+    /// # Errors
+    /// Have a look at `accept()` for the possible errors
+    pub fn parse(
+        &self,
+        body: String,
+        received_at: NaiveDateTime,
+    ) -> Result<Response, SFError> {
+        if body.is_empty() {
+            return Err(SFError::EmptyResponse);
+        }
+        let resp = Response::parse(body, received_at)?;
+        if resp.values().contains_key(SESSION_INVALID) {
+            if self.is_taken_over(received_at) {
+                return Err(SFError::SessionTakenOver);
+            }
+            return Err(SFError::ServerError(SESSION_INVALID.to_string()));
+        }
+        if let Some(lc) = resp.values().get("serverversion").copied() {
+            let version: u32 = lc.into("server version")?;
+            if version > self.options.expected_server_version {
+                warn!("Untested S&F Server version: {version}");
+                if self.options.error_on_unsupported_version {
+                    return Err(SFError::UnsupportedVersion(version));
+                }
+            }
+        }
+        Ok(resp)
+    }
+
+    /// Parses the body of a response, that the server sent for a request from
+    /// `prepare()` and updates the session id and cryptography settings with
+    /// it
+    ///
+    /// # Errors
+    /// - `UnsupportedVersion`: If `error_on_unsupported_version` is set and the
+    ///   server is running an unsupported version
+    /// - `EmptyResponse`: If the body was empty
+    /// - `ParsingError`: If the body was unexpected in some way
+    /// - `ServerError`: If the server itself responded with an ingame error
+    /// - `SessionTakenOver`: If somebody else has logged in to the character
+    ///   (see `ProtocolOptions::takeover_window`)
+    pub fn accept(
+        &mut self,
+        body: String,
+        received_at: NaiveDateTime,
+    ) -> Result<Response, SFError> {
+        let resp = self.parse(body, received_at)?;
+        self.update(&resp);
+        Ok(resp)
+    }
+
+    /// Updates the session id and cryptography settings with the response
+    /// provided
+    pub fn update(&mut self, res: &Response) {
+        self.last_response = Some(res.received_at());
+        let data = res.values();
+        if let Some(lc) = data.get("login count") {
+            self.login_count = (*lc).into("login count").unwrap_or_default();
+        }
+        if let Some(lc) = data.get("sessionid") {
+            self.session_id.clear();
+            self.session_id.push_str(lc.as_str());
+        }
+        if let Some(lc) = data.get("cryptokey") {
+            self.crypto_key.clear();
+            self.crypto_key.push_str(lc.as_str());
+        }
+        if let Some(lc) = data.get("cryptoid") {
+            self.crypto_id.clear();
+            self.crypto_id.push_str(lc.as_str());
+        }
+    }
+}
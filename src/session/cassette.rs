@@ -0,0 +1,193 @@
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::{BufRead, BufReader, BufWriter, Write},
+    path::Path,
+    sync::{Arc, Mutex, PoisonError},
+};
+
+use chrono::NaiveDateTime;
+use log::warn;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+use super::{PreparedRequest, Transport, TransportFuture, TransportResponse};
+use crate::error::SFError;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A single request and the response of the server to it. A cassette is a
+/// file with one of these as JSON per line
+pub struct CassetteEntry {
+    /// The sequence number of the request within its session
+    pub sequence: u32,
+    /// The full (encrypted) url of the request
+    pub url: Url,
+    /// The unencrypted command, that was sent
+    pub command: String,
+    /// The HTTP status code of the response
+    pub status: u16,
+    /// The raw body of the response
+    pub body: String,
+    /// The time at which the response was received
+    pub received_at: NaiveDateTime,
+}
+
+/// A transport, that sends all requests through another transport and writes
+/// them, together with the responses, to a cassette. Headers are not recorded,
+/// but keep in mind, that the login command contains the password hash of the
+/// character, so cassettes should be treated like credentials
+pub struct RecordingTransport {
+    inner: Arc<dyn Transport>,
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl std::fmt::Debug for RecordingTransport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecordingTransport")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RecordingTransport {
+    /// Creates a transport, that records everything sent through `inner` to
+    /// the writer
+    pub fn new(
+        inner: Arc<dyn Transport>,
+        writer: impl Write + Send + 'static,
+    ) -> Self {
+        Self {
+            inner,
+            writer: Mutex::new(Box::new(writer)),
+        }
+    }
+
+    /// Creates a transport, that records everything sent through `inner` to
+    /// a new cassette file at the given path. An existing file is truncated
+    ///
+    /// # Errors
+    /// Returns an error, if the file could not be created
+    pub fn create(
+        inner: Arc<dyn Transport>,
+        path: impl AsRef<Path>,
+    ) -> std::io::Result<Self> {
+        let file = File::create(path)?;
+        Ok(Self::new(inner, BufWriter::new(file)))
+    }
+
+    fn record(&self, entry: &CassetteEntry) {
+        let Ok(line) = serde_json::to_string(entry) else {
+            warn!("Could not serialize cassette entry {}", entry.sequence);
+            return;
+        };
+        let mut writer =
+            self.writer.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(e) = writeln!(writer, "{line}").and_then(|()| writer.flush())
+        {
+            warn!("Could not write cassette entry {}: {e}", entry.sequence);
+        }
+    }
+}
+
+impl Transport for RecordingTransport {
+    fn send<'a>(&'a self, request: &'a PreparedRequest) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let resp = self.inner.send(request).await?;
+            self.record(&CassetteEntry {
+                sequence: request.sequence,
+                url: request.url.clone(),
+                command: request.command.clone(),
+                status: resp.status,
+                body: resp.body.clone(),
+                received_at: resp.received_at,
+            });
+            Ok(resp)
+        })
+    }
+}
+
+#[derive(Debug)]
+/// A transport, that answers requests with the responses of a cassette, in
+/// the order they were recorded, without talking to any server. Every request
+/// has to contain the same command as the next entry on the cassette
+pub struct ReplayTransport {
+    entries: Mutex<VecDeque<CassetteEntry>>,
+}
+
+impl ReplayTransport {
+    /// Creates a transport, that replays the entries provided
+    #[must_use]
+    pub fn new(entries: impl IntoIterator<Item = CassetteEntry>) -> Self {
+        Self {
+            entries: Mutex::new(entries.into_iter().collect()),
+        }
+    }
+
+    /// Reads a cassette from the reader. Empty lines are ignored
+    ///
+    /// # Errors
+    /// - `ParsingError`: If a line is not a valid cassette entry
+    /// - `InvalidRequest`: If the cassette could not be read
+    pub fn from_reader(reader: impl BufRead) -> Result<Self, SFError> {
+        let mut entries = VecDeque::new();
+        for line in reader.lines() {
+            let line = line.map_err(|_| {
+                SFError::InvalidRequest("Could not read the cassette")
+            })?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry = serde_json::from_str(&line)
+                .map_err(|_| SFError::ParsingError("cassette entry", line))?;
+            entries.push_back(entry);
+        }
+        Ok(Self {
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Reads the cassette file at the given path
+    ///
+    /// # Errors
+    /// Look at `from_reader()` for the possible errors
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, SFError> {
+        let file = File::open(path).map_err(|_| {
+            SFError::InvalidRequest("Could not open the cassette")
+        })?;
+        Self::from_reader(BufReader::new(file))
+    }
+
+    /// The amount of entries, that have not yet been replayed
+    #[must_use]
+    pub fn remaining(&self) -> usize {
+        self.entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .len()
+    }
+}
+
+impl Transport for ReplayTransport {
+    fn send<'a>(&'a self, request: &'a PreparedRequest) -> TransportFuture<'a> {
+        let next = self
+            .entries
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front();
+        Box::pin(async move {
+            let entry = next.ok_or(SFError::InvalidRequest(
+                "The cassette has no more responses",
+            ))?;
+            if entry.command != request.command {
+                return Err(SFError::InvalidRequest(
+                    "The request does not match the next cassette entry",
+                ));
+            }
+            Ok(TransportResponse {
+                status: entry.status,
+                body: entry.body,
+                received_at: entry.received_at,
+            })
+        })
+    }
+}
@@ -0,0 +1,85 @@
+use std::sync::{LazyLock, Mutex, PoisonError};
+
+use log::info;
+
+use super::{PreparedRequest, Transport, TransportFuture, TransportResponse};
+
+/// The response, that a `DryRunTransport` sends by default. This is the
+/// smallest response to a login, or an update, that a `GameState` can be
+/// built from. It belongs to a level 1 character called `DryRun`
+pub static DRY_RUN_RESPONSE: LazyLock<String> = LazyLock::new(|| {
+    let mut save = [0; 700];
+    // The level
+    save[7] = 1;
+    // The race and class
+    save[27] = 1;
+    save[29] = 1;
+    // Shops always have to offer some item, so both of them are full of
+    // breastplates
+    for pos in (288..360).step_by(12).chain((361..433).step_by(12)) {
+        if let Some(typ) = save.get_mut(pos) {
+            *typ = 3;
+        }
+    }
+    let save: Vec<_> = save.iter().map(i64::to_string).collect();
+    format!("ownplayername:DryRun&ownplayersave:{}", save.join("/"))
+});
+
+#[derive(Debug)]
+/// A transport, that does not send anything. Every request is fully built and
+/// encrypted like normal, but then only recorded and answered with a canned
+/// response. This allows you to step through a strategy on a real account and
+/// look at the commands it would send, without any side effects
+pub struct DryRunTransport {
+    response: String,
+    requests: Mutex<Vec<PreparedRequest>>,
+}
+
+impl Default for DryRunTransport {
+    fn default() -> Self {
+        Self::new(DRY_RUN_RESPONSE.as_str())
+    }
+}
+
+impl DryRunTransport {
+    /// Creates a transport, that answers every request with the given body.
+    /// This should usually be the response to a `Command::Update`, so that
+    /// the game state can be updated with it
+    #[must_use]
+    pub fn new(response: impl Into<String>) -> Self {
+        Self {
+            response: response.into(),
+            requests: Mutex::default(),
+        }
+    }
+
+    /// All requests, that would have been sent so far, in order. The
+    /// unencrypted command of each of them is in `PreparedRequest::command`
+    #[must_use]
+    pub fn requests(&self) -> Vec<PreparedRequest> {
+        self.requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Returns all requests, that would have been sent so far and forgets them
+    #[must_use]
+    pub fn take_requests(&self) -> Vec<PreparedRequest> {
+        std::mem::take(
+            &mut *self.requests.lock().unwrap_or_else(PoisonError::into_inner),
+        )
+    }
+}
+
+impl Transport for DryRunTransport {
+    fn send<'a>(&'a self, request: &'a PreparedRequest) -> TransportFuture<'a> {
+        info!("Dry run of {} ({})", request.command, request.url);
+        self.requests
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(request.clone());
+        let response = TransportResponse::new(200, self.response.clone());
+        Box::pin(async move { Ok(response) })
+    }
+}
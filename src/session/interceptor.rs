@@ -0,0 +1,89 @@
+use std::fmt::Debug;
+
+use log::{log, Level};
+
+use super::Response;
+use crate::{command::Command, error::SFError};
+
+#[derive(Debug, Clone, PartialEq)]
+/// What should happen with a command, that is about to be sent
+pub enum Verdict {
+    /// Send the command as it is
+    Allow,
+    /// Do not send the command. The session returns a `CommandDenied` error
+    /// with the given reason instead
+    Deny(String),
+    /// Send this command instead
+    Rewrite(Command),
+}
+
+/// Hooks into every command, that a session sends. Interceptors are called in
+/// the order they were added to the session. Every (re)try of a command is
+/// intercepted separately, including the logins, that a session does on its
+/// own. Requests, that are built manually with `Session::prepare()`, are not
+/// intercepted
+pub trait Interceptor: Debug + Send + Sync {
+    /// Called before the command is sent. If an earlier interceptor rewrote
+    /// the command, this sees the rewritten command. Once an interceptor
+    /// denies the command, no further interceptors are asked
+    fn before_send(&self, command: &Command) -> Verdict {
+        _ = command;
+        Verdict::Allow
+    }
+
+    /// Called with the result of every command, that was actually sent
+    fn after_receive(
+        &self,
+        command: &Command,
+        result: &Result<Response, SFError>,
+    ) {
+        _ = (command, result);
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+/// Logs every command and whether or not it succeeded. Credentials in the
+/// commands are not logged (see `Command::redacted()`)
+pub struct LoggingInterceptor {
+    /// The level, that the messages are logged with
+    pub level: Level,
+}
+
+impl Default for LoggingInterceptor {
+    fn default() -> Self {
+        Self { level: Level::Info }
+    }
+}
+
+impl Interceptor for LoggingInterceptor {
+    fn before_send(&self, command: &Command) -> Verdict {
+        log!(self.level, "Sending {:?}", command.redacted());
+        Verdict::Allow
+    }
+
+    fn after_receive(
+        &self,
+        command: &Command,
+        result: &Result<Response, SFError>,
+    ) {
+        match result {
+            Ok(_) => log!(self.level, "{:?} succeeded", command.redacted()),
+            Err(e) => log!(self.level, "{:?} failed: {e}", command.redacted()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+/// Denies every command, that could spend mushrooms (see
+/// `Command::uses_mushroom()`)
+pub struct MushroomGuard;
+
+impl Interceptor for MushroomGuard {
+    fn before_send(&self, command: &Command) -> Verdict {
+        if command.uses_mushroom() {
+            Verdict::Deny(format!("{command:?} would spend a mushroom"))
+        } else {
+            Verdict::Allow
+        }
+    }
+}
@@ -0,0 +1,367 @@
+use std::{
+    borrow::Borrow,
+    collections::HashMap,
+    future::Future,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex, PoisonError},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local};
+use tokio::{
+    sync::{OwnedMutexGuard, OwnedSemaphorePermit, Semaphore},
+    task::JoinSet,
+};
+
+use super::{ConnectionOptions, ServerConnection, Session, SimpleSession};
+use crate::{
+    command::Command,
+    error::{ConnectionErrorKind, SFError},
+    gamestate::GameState,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+/// Identifies a character within an `AccountPool`
+pub struct CharacterId(usize);
+
+#[derive(Debug, Clone, Default)]
+/// What the pool knows about a character, without having to wait for the
+/// command it might currently be sending
+pub struct CharacterStatus {
+    /// The name of the character
+    pub username: String,
+    /// Whether the character currently has a valid game state. If not, the
+    /// next command will log it in first
+    pub logged_in: bool,
+    /// The message of the last error of a command, or step of
+    /// `AccountPool::run()`. This is cleared, once a command succeeds again
+    pub last_error: Option<String>,
+    /// The time at which `AccountPool::run()` will next act for this
+    /// character. `None`, if it is not scheduled
+    pub next_wakeup: Option<DateTime<Local>>,
+}
+
+#[derive(Debug)]
+struct PoolEntry {
+    session: Arc<tokio::sync::Mutex<SimpleSession>>,
+    status: Mutex<CharacterStatus>,
+}
+
+impl PoolEntry {
+    fn status(&self) -> std::sync::MutexGuard<'_, CharacterStatus> {
+        self.status.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+#[derive(Debug)]
+/// Manages a lot of characters across servers and SSO accounts. All
+/// characters on the same server share one `ServerConnection` (and thus its
+/// client and rate limit). Commands of one character are always sent one
+/// after another, while different characters act concurrently, up to a
+/// global limit
+pub struct AccountPool {
+    options: ConnectionOptions,
+    connections: HashMap<url::Url, ServerConnection>,
+    entries: Vec<Arc<PoolEntry>>,
+    permits: Arc<Semaphore>,
+    error_delay: Duration,
+}
+
+impl AccountPool {
+    /// Creates an empty pool, in which at most `max_concurrent` characters
+    /// can act at the same time
+    #[must_use]
+    pub fn new(max_concurrent: usize) -> Self {
+        Self::with_options(max_concurrent, ConnectionOptions::default())
+    }
+
+    /// Creates an empty pool, that uses the given options for all connections
+    /// it creates
+    #[must_use]
+    pub fn with_options(
+        max_concurrent: usize,
+        options: ConnectionOptions,
+    ) -> Self {
+        Self {
+            options,
+            connections: HashMap::new(),
+            entries: Vec::new(),
+            permits: Arc::new(Semaphore::new(max_concurrent.max(1))),
+            error_delay: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets how long `run()` waits before it acts for a character again, after
+    /// its step has failed. The default is 30 seconds
+    pub fn set_error_delay(&mut self, delay: Duration) {
+        self.error_delay = delay;
+    }
+
+    /// Adds a normal (not SSO) character to the pool. The character is not
+    /// logged in until its first command
+    ///
+    /// # Errors
+    /// - `ConnectionError`: If no connection to the server could be created
+    pub fn add_character(
+        &mut self,
+        username: &str,
+        password: &str,
+        server_url: &str,
+    ) -> Result<CharacterId, SFError> {
+        let connection = ServerConnection::new_with_options(
+            server_url,
+            self.options.clone(),
+        )
+        .ok_or(SFError::connection(ConnectionErrorKind::Other))?;
+        let connection = self
+            .connections
+            .entry(connection.url.clone())
+            .or_insert(connection);
+        let session = Session::new(username, password, connection.clone());
+        Ok(self.push(session))
+    }
+
+    #[cfg(feature = "sso")]
+    /// Logs in the S&F SSO account and adds all of its characters to the pool
+    ///
+    /// # Errors
+    /// Have a look at `SFAccount::login()` and `SFAccount::characters()` for
+    /// the possible errors
+    pub async fn add_sf_account(
+        &mut self,
+        username: &str,
+        password: &str,
+    ) -> Result<Vec<CharacterId>, SFError> {
+        let account = crate::sso::SFAccount::login_with_options(
+            username.to_string(),
+            password.to_string(),
+            self.options.clone(),
+        )
+        .await?;
+        Ok(account
+            .characters()
+            .await?
+            .into_iter()
+            .flatten()
+            .map(|session| self.add_session(session))
+            .collect())
+    }
+
+    /// Adds an existing session to the pool. It is moved onto the shared
+    /// connection of its server, if the pool already has one. A transport,
+    /// that has been set explicitly (see `Session::set_transport()`), is kept
+    /// and only the rate limit of the server is shared
+    pub fn add_session(&mut self, mut session: Session) -> CharacterId {
+        if let Some(connection) = self.connections.get(session.server_url()) {
+            if session.default_transport && connection.default_transport {
+                session.transport = connection.session_transport();
+            }
+            session.server_limit.clone_from(&connection.limit);
+        } else {
+            let connection = ServerConnection {
+                url: session.server_url().clone(),
+                transport: session.transport.clone(),
+                limit: session.server_limit.clone(),
+                default_transport: session.default_transport,
+                options: session.options.clone(),
+            };
+            self.connections.insert(connection.url.clone(), connection);
+        }
+        self.push(session)
+    }
+
+    fn push(&mut self, session: Session) -> CharacterId {
+        let status = CharacterStatus {
+            username: session.username().to_string(),
+            ..Default::default()
+        };
+        self.entries.push(Arc::new(PoolEntry {
+            session: Arc::new(tokio::sync::Mutex::new(SimpleSession::new(
+                session, None,
+            ))),
+            status: Mutex::new(status),
+        }));
+        CharacterId(self.entries.len() - 1)
+    }
+
+    /// The amount of characters in the pool
+    #[must_use]
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Checks if the pool does not contain any characters
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The ids of all characters in the pool
+    pub fn characters(&self) -> impl Iterator<Item = CharacterId> {
+        (0..self.entries.len()).map(CharacterId)
+    }
+
+    /// The current status of the character, or `None`, if the id does not
+    /// belong to this pool
+    #[must_use]
+    pub fn status(&self, id: CharacterId) -> Option<CharacterStatus> {
+        self.entries.get(id.0).map(|a| a.status().clone())
+    }
+
+    /// Waits until the character is not in use anymore and a slot of the
+    /// concurrency limit is free. The character can then be used exclusively
+    /// through the guard, until it is dropped
+    ///
+    /// # Errors
+    /// - `InvalidRequest`: If the id does not belong to this pool
+    pub async fn lock(
+        &self,
+        id: CharacterId,
+    ) -> Result<CharacterGuard, SFError> {
+        let entry = self.entries.get(id.0).ok_or(SFError::InvalidRequest(
+            "The character is not part of this pool",
+        ))?;
+        Ok(CharacterGuard::acquire(entry.clone(), self.permits.clone()).await)
+    }
+
+    /// Repeatedly calls `step` for every character in the pool, until all of
+    /// them are done. Characters are driven concurrently, within the limit of
+    /// the pool. `step` returns the time at which it wants to be called again
+    /// for the character, or `None`, if the character is done. If `step`
+    /// fails, it is called again after the error delay, or once the character
+    /// is not paused by its `YieldPolicy` anymore
+    ///
+    /// # Panics
+    /// If `step` panics, the status of the character is updated, all other
+    /// characters are stopped and the panic is resumed
+    pub async fn run<F, Fut>(&self, step: F)
+    where
+        F: Fn(CharacterId, CharacterGuard) -> Fut + Clone + Send + 'static,
+        Fut: Future<Output = Result<Option<DateTime<Local>>, SFError>> + Send,
+    {
+        let mut tasks = JoinSet::new();
+        let mut ids = HashMap::new();
+        for (idx, entry) in self.entries.iter().enumerate() {
+            let entry = entry.clone();
+            let permits = self.permits.clone();
+            let step = step.clone();
+            let error_delay = self.error_delay;
+            let task = tasks.spawn(async move {
+                loop {
+                    let wakeup = entry.status().next_wakeup;
+                    if let Some(wakeup) = wakeup {
+                        let wait = (wakeup - Local::now()).to_std();
+                        tokio::time::sleep(wait.unwrap_or_default()).await;
+                    }
+                    let guard =
+                        CharacterGuard::acquire(entry.clone(), permits.clone())
+                            .await;
+                    let res = step(CharacterId(idx), guard).await;
+                    // Characters, that somebody else is playing, are left
+                    // alone until the pause of their yield policy is over
+                    let paused_until = entry
+                        .session
+                        .try_lock()
+                        .ok()
+                        .and_then(|a| a.paused_until());
+                    let mut status = entry.status();
+                    match res {
+                        Ok(Some(next)) => status.next_wakeup = Some(next),
+                        Ok(None) => {
+                            status.next_wakeup = None;
+                            break;
+                        }
+                        Err(e) => {
+                            status.last_error = Some(e.to_string());
+                            status.next_wakeup =
+                                Some(paused_until.unwrap_or_else(|| {
+                                    Local::now() + error_delay
+                                }));
+                        }
+                    }
+                }
+            });
+            ids.insert(task.id(), idx);
+        }
+        while let Some(res) = tasks.join_next_with_id().await {
+            let Err(e) = res else {
+                continue;
+            };
+            if !e.is_panic() {
+                continue;
+            }
+            if let Some(entry) =
+                ids.get(&e.id()).and_then(|a| self.entries.get(*a))
+            {
+                let mut status = entry.status();
+                status.last_error = Some("The step panicked".to_string());
+                status.next_wakeup = None;
+            }
+            tasks.abort_all();
+            std::panic::resume_unwind(e.into_panic());
+        }
+    }
+}
+
+#[derive(Debug)]
+/// Exclusive access to a character of an `AccountPool`. While this exists, no
+/// other task can send commands for the character and one slot of the
+/// concurrency limit of the pool is taken
+pub struct CharacterGuard {
+    session: OwnedMutexGuard<SimpleSession>,
+    entry: Arc<PoolEntry>,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl CharacterGuard {
+    async fn acquire(entry: Arc<PoolEntry>, permits: Arc<Semaphore>) -> Self {
+        // We lock the character first, so that waiting for a busy character
+        // does not block a slot, that another character could use
+        let session = entry.session.clone().lock_owned().await;
+        #[allow(clippy::expect_used)]
+        let permit = permits
+            .acquire_owned()
+            .await
+            .expect("the semaphore of the pool is never closed");
+        Self {
+            session,
+            entry,
+            _permit: permit,
+        }
+    }
+
+    /// Sends the command just like `SimpleSession::send_command()` and
+    /// records the outcome in the status of the character
+    ///
+    /// # Errors
+    /// Have a look at `SimpleSession::send_command()` for the possible errors
+    pub async fn send_command<T: Borrow<Command>>(
+        &mut self,
+        cmd: T,
+    ) -> Result<&mut GameState, SFError> {
+        let res = self.session.send_command(cmd).await.map(|_| ());
+        let mut status = self.entry.status();
+        status.logged_in = self.session.gamestate.is_some();
+        status.last_error = res.as_ref().err().map(|e| e.to_string());
+        drop(status);
+        res?;
+        self.session
+            .gamestate
+            .as_mut()
+            .ok_or(SFError::InvalidRequest("The character has no game state"))
+    }
+}
+
+impl Deref for CharacterGuard {
+    type Target = SimpleSession;
+
+    fn deref(&self) -> &Self::Target {
+        &self.session
+    }
+}
+
+impl DerefMut for CharacterGuard {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.session
+    }
+}
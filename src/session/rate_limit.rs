@@ -0,0 +1,152 @@
+use std::{
+    sync::{Arc, Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+/// Limits how fast requests are sent to the server. The server limit is shared
+/// by all sessions, that were created from the same `ServerConnection`, or
+/// clones of it. `AccountPool` shares it between all of its sessions on the
+/// same server and the characters of one `SFAccount::characters()` call share
+/// it per server as well. Connections, that were created independently (for
+/// example with different proxies) are never throttled together. By default,
+/// nothing is limited
+pub struct RateLimit {
+    /// The minimum time between two requests of one session. Zero disables
+    /// this limit
+    pub session_spacing: Duration,
+    /// The amount of requests, that all sessions on the server connection can
+    /// send in quick succession, before they get throttled. 0 disables the
+    /// server limit
+    pub server_burst: u32,
+    /// The time it takes for the server limit to allow one more request
+    pub server_interval: Duration,
+}
+
+impl Default for RateLimit {
+    fn default() -> Self {
+        Self {
+            session_spacing: Duration::ZERO,
+            server_burst: 0,
+            server_interval: Duration::from_millis(200),
+        }
+    }
+}
+
+impl RateLimit {
+    /// A new bucket for the server limit. Sessions share it by sharing their
+    /// `ServerConnection`
+    pub(super) fn server_bucket(&self) -> Option<Arc<TokenBucket>> {
+        if self.server_burst == 0 || self.server_interval.is_zero() {
+            return None;
+        }
+        Some(Arc::new(TokenBucket::new(
+            self.server_burst,
+            self.server_interval,
+        )))
+    }
+
+    /// The bucket, that enforces the spacing within one session
+    pub(super) fn session_bucket(&self) -> Option<Arc<TokenBucket>> {
+        if self.session_spacing.is_zero() {
+            return None;
+        }
+        Some(Arc::new(TokenBucket::new(1, self.session_spacing)))
+    }
+}
+
+#[derive(Debug)]
+/// A token bucket, that holds up to `capacity` tokens and gains one more
+/// token every `interval`. Every request takes one token
+pub(super) struct TokenBucket {
+    capacity: f64,
+    interval: Duration,
+    /// The (possibly negative) amount of tokens at the given time. Negative
+    /// values are reservations of requests, that are currently waiting
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn new(capacity: u32, interval: Duration) -> Self {
+        Self {
+            capacity: f64::from(capacity),
+            interval,
+            state: Mutex::new((f64::from(capacity), Instant::now())),
+        }
+    }
+
+    /// Takes a token at the given time and returns the time, that has to be
+    /// waited, before the request can be sent
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut state =
+            self.state.lock().unwrap_or_else(PoisonError::into_inner);
+        let (tokens, last) = *state;
+        let refilled = now.saturating_duration_since(last).as_secs_f64()
+            / self.interval.as_secs_f64();
+        let tokens = (tokens + refilled).min(self.capacity) - 1.0;
+        *state = (tokens, now.max(last));
+        if tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            self.interval.mul_f64(-tokens)
+        }
+    }
+
+    /// Waits until the bucket allows another request
+    pub(super) async fn acquire(&self) {
+        let wait = self.reserve(Instant::now());
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bucket_allows_bursts_and_refills() {
+        let interval = Duration::from_millis(100);
+        let bucket = TokenBucket::new(2, interval);
+        let start = Instant::now();
+        assert_eq!(bucket.reserve(start), Duration::ZERO);
+        assert_eq!(bucket.reserve(start), Duration::ZERO);
+        // The burst is used up, so the next requests have to queue up
+        assert_eq!(bucket.reserve(start), interval);
+        assert_eq!(bucket.reserve(start), interval * 2);
+
+        // Waiting for the reservations to clear refills the bucket again,
+        // but never beyond its capacity
+        let later = start + interval * 10;
+        assert_eq!(bucket.reserve(later), Duration::ZERO);
+        assert_eq!(bucket.reserve(later), Duration::ZERO);
+        assert_eq!(bucket.reserve(later), interval);
+
+        // Times from before the last reservation do not refill anything
+        assert_eq!(bucket.reserve(start), interval * 2);
+    }
+
+    #[tokio::test]
+    async fn acquire_waits_for_a_token() {
+        let interval = Duration::from_millis(50);
+        let bucket = TokenBucket::new(1, interval);
+        let start = Instant::now();
+        bucket.acquire().await;
+        assert!(start.elapsed() < interval);
+        bucket.acquire().await;
+        bucket.acquire().await;
+        assert!(start.elapsed() >= interval * 2);
+    }
+
+    #[test]
+    fn server_buckets_are_only_created_when_limited() {
+        let limit = RateLimit {
+            server_burst: 3,
+            ..Default::default()
+        };
+        assert!(limit.server_bucket().is_some());
+        assert!(RateLimit::default().server_bucket().is_none());
+    }
+}
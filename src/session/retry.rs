@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+#[derive(Debug, Clone, PartialEq)]
+/// Decides how `Session::send_command()` reacts to errors. Connection errors
+/// and invalidated sessions (see `ErrorClass`) are retried after a backoff, if
+/// the command is idempotent. Invalidated sessions are logged in again before
+/// that. Ingame errors are never retried. By default, nothing is retried
+pub struct RetryPolicy {
+    /// The maximum amount of times a command will be sent again after the
+    /// first attempt failed. 0 disables retries
+    pub max_retries: u32,
+    /// The time to wait before the first retry
+    pub initial_backoff: Duration,
+    /// The factor, by which the wait time grows after each retry
+    pub backoff_factor: f64,
+    /// The longest time to wait between two retries
+    pub max_backoff: Duration,
+    /// The share of the backoff (0.0 to 1.0), by which each wait is randomly
+    /// shortened, or lengthened. This keeps many sessions, that failed at the
+    /// same time, from all retrying at the same time
+    pub jitter: f64,
+    /// Whether or not the session should be logged in again (and have its SSO
+    /// credentials renewed), if the server says, that it is not valid anymore
+    pub relogin: bool,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_retries: 0,
+            initial_backoff: Duration::from_millis(500),
+            backoff_factor: 2.0,
+            max_backoff: Duration::from_secs(30),
+            jitter: 0.1,
+            relogin: true,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy, that retries up to `max_retries` times with the default
+    /// backoff
+    #[must_use]
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            ..Default::default()
+        }
+    }
+
+    /// The time to wait before the given retry. The first retry is 1
+    #[must_use]
+    pub fn backoff(&self, retry: u32) -> Duration {
+        let exp = i32::try_from(retry.saturating_sub(1)).unwrap_or(i32::MAX);
+        let backoff = self
+            .initial_backoff
+            .mul_f64(self.backoff_factor.max(1.0).powi(exp).min(1e6));
+        backoff.min(self.max_backoff)
+    }
+
+    /// The `backoff()` before the given retry with the jitter applied. This
+    /// is what `Session::send_command()` actually waits
+    #[must_use]
+    pub fn jittered_backoff(&self, retry: u32) -> Duration {
+        let jitter = match self.jitter {
+            j if j.is_nan() => 0.0,
+            j => j.clamp(0.0, 1.0),
+        };
+        let factor = 1.0 + jitter * (fastrand::f64() * 2.0 - 1.0);
+        self.backoff(retry).mul_f64(factor)
+    }
+}
@@ -0,0 +1,79 @@
+use std::{borrow::Borrow, sync::Arc};
+
+use tokio::sync::{Mutex, MutexGuard};
+
+use super::{Response, Session};
+use crate::{command::Command, error::SFError};
+
+#[derive(Debug, Clone)]
+/// A handle to a session, that can be cloned cheaply and used from many tasks
+/// at once. Unlike clones of a `Session`, all handles see the same login and
+/// crypto state, so a login through one handle is immediately used by all
+/// others. Commands are sent one after another, in the order in which they
+/// were issued, and every caller gets the response to its own command back.
+///
+/// If the future of a command is dropped, while it is being sent, the session
+/// will not see the response. Should that response have changed the login
+/// state, the next command will fail and has to be retried after a login
+pub struct SharedSession {
+    session: Arc<Mutex<Session>>,
+}
+
+impl From<Session> for SharedSession {
+    fn from(value: Session) -> Self {
+        Self::new(value)
+    }
+}
+
+impl SharedSession {
+    /// Wraps the session, so that it can be shared between tasks
+    #[must_use]
+    pub fn new(session: Session) -> Self {
+        Self {
+            session: Arc::new(Mutex::new(session)),
+        }
+    }
+
+    /// Logs in the session. Have a look at `Session::login()` for more
+    /// information
+    ///
+    /// # Errors
+    /// Look at `Session::send_command()` to get a full overview of all the
+    /// possible errors
+    pub async fn login(&self) -> Result<Response, SFError> {
+        self.session.lock().await.login().await
+    }
+
+    /// Waits for all commands, that were issued before, to finish and then
+    /// sends the command. Have a look at `Session::send_command()` for more
+    /// information
+    ///
+    /// # Errors
+    /// Look at `Session::send_command()` to get a full overview of all the
+    /// possible errors
+    pub async fn send_command<T: Borrow<Command>>(
+        &self,
+        command: T,
+    ) -> Result<Response, SFError> {
+        self.session.lock().await.send_command(command).await
+    }
+
+    /// Waits for all commands, that were issued before, to finish and then
+    /// gives you exclusive access to the session. No other handle can send a
+    /// command, until the guard is dropped. This is useful, if a few commands
+    /// have to be sent directly after one another
+    pub async fn lock(&self) -> MutexGuard<'_, Session> {
+        self.session.lock().await
+    }
+
+    /// Returns the session, if this is the last handle to it. Otherwise the
+    /// handle is given back
+    ///
+    /// # Errors
+    /// Returns this handle, if other handles to the session still exist
+    pub fn try_into_inner(self) -> Result<Session, Self> {
+        Arc::try_unwrap(self.session)
+            .map(Mutex::into_inner)
+            .map_err(|session| Self { session })
+    }
+}
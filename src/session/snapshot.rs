@@ -0,0 +1,120 @@
+use std::sync::{atomic::AtomicU32, Arc};
+
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+use super::{LoginData, PWHash, Response, ServerConnection, Session};
+use crate::{
+    command::Command,
+    error::{ErrorClass, SFError},
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Everything necessary to continue a logged in session in another process,
+/// without having to log in again. Keep in mind, that this contains the
+/// password hash of the character, so it should be stored like credentials
+pub struct SessionSnapshot {
+    username: String,
+    pw_hash: PWHash,
+    session_id: String,
+    crypto_id: String,
+    crypto_key: String,
+    login_count: u32,
+    command_count: u32,
+    #[serde(default)]
+    last_response: Option<NaiveDateTime>,
+}
+
+#[derive(Debug)]
+/// The way, in which `Session::resume()` was able to continue the session
+pub enum Resumed {
+    /// The server accepted the stored session. Contains its response to a
+    /// `Command::Update`
+    Continued(Response),
+    /// The server rejected the stored session, so the session logged in
+    /// again. Contains the full login response
+    LoggedIn(Response),
+}
+
+impl Resumed {
+    /// The response of the server, regardless of how the session was resumed
+    #[must_use]
+    pub fn response(&self) -> &Response {
+        match self {
+            Resumed::Continued(resp) | Resumed::LoggedIn(resp) => resp,
+        }
+    }
+}
+
+impl Session {
+    /// Captures the current state of the session, so that it can later be
+    /// continued with `resume()`. Commands sent after this is taken will make
+    /// the snapshot outdated, so this should be taken right before the session
+    /// is dropped
+    ///
+    /// # Errors
+    /// - `InvalidRequest`: If this is an SSO session. These are bound to their
+    ///   `SFAccount` and can not be restored on their own
+    pub fn snapshot(&self) -> Result<SessionSnapshot, SFError> {
+        #[allow(irrefutable_let_patterns)]
+        let LoginData::Basic { username, pw_hash } = &self.login_data
+        else {
+            return Err(SFError::InvalidRequest(
+                "Can not take a snapshot of a sso session",
+            ));
+        };
+        Ok(SessionSnapshot {
+            username: username.clone(),
+            pw_hash: pw_hash.clone(),
+            session_id: self.state.session_id.clone(),
+            crypto_id: self.state.crypto_id.clone(),
+            crypto_key: self.state.crypto_key.clone(),
+            login_count: self.state.login_count,
+            command_count: self
+                .state
+                .command_count
+                .load(std::sync::atomic::Ordering::SeqCst),
+            last_response: self.state.last_response,
+        })
+    }
+
+    /// Continues a session from a snapshot on the given server. To check, if
+    /// the server still knows about the session, an update is sent. If the
+    /// server rejects it, because the session has expired, the session logs
+    /// in again, just like `login()`
+    ///
+    /// # Errors
+    /// - `SessionTakenOver`: If somebody else has logged in to the character
+    ///   since the snapshot was taken (see
+    ///   `ConnectionOptions::takeover_window`). The session is not logged in
+    ///   again, so that they are not kicked out
+    ///
+    /// Look at `send_command()` to get a full overview of all the other
+    /// possible errors
+    pub async fn resume(
+        snapshot: SessionSnapshot,
+        server: ServerConnection,
+    ) -> Result<(Session, Resumed), SFError> {
+        let mut session =
+            Session::new_hashed(&snapshot.username, snapshot.pw_hash, server);
+        let state = &mut session.state;
+        state.session_id = snapshot.session_id;
+        state.crypto_id = snapshot.crypto_id;
+        state.crypto_key = snapshot.crypto_key;
+        state.login_count = snapshot.login_count;
+        state.command_count = Arc::new(AtomicU32::new(snapshot.command_count));
+        state.last_response = snapshot.last_response;
+
+        match session.send_command_raw(Command::Update).await {
+            Ok(resp) => {
+                session.update(&resp);
+                Ok((session, Resumed::Continued(resp)))
+            }
+            Err(e) if e.class() == ErrorClass::Session => {
+                let resp = session.login().await?;
+                Ok((session, Resumed::LoggedIn(resp)))
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
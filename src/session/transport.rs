@@ -0,0 +1,89 @@
+use std::{fmt::Debug, future::Future, pin::Pin};
+
+use chrono::{Local, NaiveDateTime};
+use reqwest::Client;
+
+use super::{reqwest_client, ConnectionOptions, PreparedRequest};
+use crate::error::SFError;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+/// The raw answer of the server to a `PreparedRequest`
+pub struct TransportResponse {
+    /// The HTTP status code
+    pub status: u16,
+    /// The (decompressed) body of the response
+    pub body: String,
+    /// The time at which the response was received. Timers in the response
+    /// are relative to this
+    pub received_at: NaiveDateTime,
+}
+
+impl TransportResponse {
+    /// Creates a new response with the given status code and body, that has
+    /// been received just now
+    #[must_use]
+    pub fn new(status: u16, body: String) -> Self {
+        Self {
+            status,
+            body,
+            received_at: Local::now().naive_local(),
+        }
+    }
+}
+
+/// The future returned by `Transport::send`
+pub type TransportFuture<'a> = Pin<
+    Box<dyn Future<Output = Result<TransportResponse, SFError>> + Send + 'a>,
+>;
+
+/// Something, that is able to send requests to the game server and get the
+/// response back. By default, sessions use a `ReqwestTransport`, but this can
+/// be swapped out to mock the server, record traffic, or use a different HTTP
+/// stack
+pub trait Transport: Debug + Send + Sync {
+    /// Sends the request and returns the status and body of the response. A
+    /// non success status is not an error at this point. Errors should only be
+    /// returned, if the request could not be sent, or the response could not
+    /// be received
+    fn send<'a>(&'a self, request: &'a PreparedRequest) -> TransportFuture<'a>;
+}
+
+#[derive(Debug, Clone)]
+/// The default transport, that sends all requests via reqwest
+pub struct ReqwestTransport {
+    // We keep this instead of creating a new one, because as per the reqwest
+    // docs: "The Client holds a connection pool internally, so it is advised
+    // that you create one and reuse it."
+    client: Client,
+}
+
+impl ReqwestTransport {
+    /// Creates a transport, that sends all requests with the given client
+    #[must_use]
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    /// Creates a transport with a new client, that is configured with the
+    /// options provided
+    #[must_use]
+    pub fn with_options(options: &ConnectionOptions) -> Option<Self> {
+        Some(Self::new(reqwest_client(options)?))
+    }
+}
+
+impl Transport for ReqwestTransport {
+    fn send<'a>(&'a self, request: &'a PreparedRequest) -> TransportFuture<'a> {
+        Box::pin(async move {
+            let mut req = self.client.get(request.url.as_str());
+            for (name, value) in &request.headers {
+                req = req.header(name, value);
+            }
+            let resp = req.send().await?;
+            let status = resp.status().as_u16();
+            let body = resp.text().await?;
+            Ok(TransportResponse::new(status, body))
+        })
+    }
+}
@@ -0,0 +1,58 @@
+use std::{fmt::Debug, sync::Arc, time::Duration};
+
+/// Called with the name of the character, when somebody else has logged in to
+/// it
+pub type TakenOverCallback = Arc<dyn Fn(&str) + Send + Sync>;
+
+#[derive(Clone)]
+/// Decides what a `SimpleSession` does, after somebody else has logged in to
+/// its character (see `SFError::SessionTakenOver`). Instead of logging in
+/// again right away and kicking them out, the session leaves the character
+/// alone for a while. Commands during the pause fail without being sent and
+/// the first command after it logs in again. This has to be enabled with
+/// `SimpleSession::set_yield_policy()`
+pub struct YieldPolicy {
+    /// How long to wait before logging in again. If this is zero, the next
+    /// command logs in again immediately
+    pub pause: Duration,
+    /// Called once every time the session has been taken over. This is
+    /// a good place to let the operator know, that a human is playing
+    pub on_taken_over: Option<TakenOverCallback>,
+}
+
+impl Debug for YieldPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("YieldPolicy")
+            .field("pause", &self.pause)
+            .field("on_taken_over", &self.on_taken_over.is_some())
+            .finish()
+    }
+}
+
+impl Default for YieldPolicy {
+    fn default() -> Self {
+        Self::new(Duration::from_secs(15 * 60))
+    }
+}
+
+impl YieldPolicy {
+    /// A policy, that pauses for the given duration without a callback
+    #[must_use]
+    pub fn new(pause: Duration) -> Self {
+        Self {
+            pause,
+            on_taken_over: None,
+        }
+    }
+
+    /// Sets the callback, that is called, when the session has been taken
+    /// over
+    #[must_use]
+    pub fn on_taken_over(
+        mut self,
+        callback: impl Fn(&str) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_taken_over = Some(Arc::new(callback));
+        self
+    }
+}
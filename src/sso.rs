@@ -11,7 +11,7 @@ use tokio::sync::Mutex;
 use url::Url;
 
 use crate::{
-    error::SFError,
+    error::{ConnectionErrorKind, SFError},
     misc::sha1_hash,
     session::{reqwest_client, ConnectionOptions, PWHash, Session},
 };
@@ -134,7 +134,8 @@ impl SFAccount {
                 uuid: String::new(),
                 bearer_token: String::new(),
             },
-            client: reqwest_client(&options).ok_or(SFError::ConnectionError)?,
+            client: reqwest_client(&options)
+                .ok_or(SFError::connection(ConnectionErrorKind::Other))?,
             options,
         };
 
@@ -226,11 +227,17 @@ impl SFAccount {
 
         let account = Arc::new(Mutex::new(self));
 
+        let mut servers = HashMap::new();
         let mut chars = vec![];
         for char in characters {
             chars.push(
-                Session::from_sso_char(char, account.clone(), &server_lookup)
-                    .await,
+                Session::from_sso_char(
+                    char,
+                    account.clone(),
+                    &server_lookup,
+                    &mut servers,
+                )
+                .await,
             );
         }
 
@@ -263,7 +270,7 @@ async fn send_api_request(
     method: APIRequest,
 ) -> Result<Value, SFError> {
     let mut url = url::Url::parse("https://sso.playa-games.com")
-        .map_err(|_| SFError::ConnectionError)?;
+        .map_err(|_| SFError::InvalidRequest("Invalid SSO url"))?;
     url.set_path(endpoint);
 
     let mut request = match method {
@@ -286,18 +293,14 @@ async fn send_api_request(
     headers.insert(
         REFERER,
         HeaderValue::from_str(url.authority())
-            .map_err(|_| SFError::ConnectionError)?,
+            .map_err(|_| SFError::InvalidRequest("Invalid SSO referer"))?,
     );
 
-    let res = request
-        .headers(headers)
-        .send()
-        .await
-        .map_err(|_| SFError::ConnectionError)?;
+    let res = request.headers(headers).send().await?;
     if !res.status().is_success() {
-        return Err(SFError::ConnectionError);
+        return Err(SFError::http_status(res.status().as_u16()));
     }
-    let text = res.text().await.map_err(|_| SFError::ConnectionError)?;
+    let text = res.text().await?;
 
     #[derive(Debug, Serialize, Deserialize)]
     struct APIResponse {
@@ -311,13 +314,20 @@ async fn send_api_request(
         .map_err(|_| SFError::ParsingError("API response", text))?;
 
     if !resp.success {
-        return Err(SFError::ConnectionError);
+        let message = match resp.message {
+            Some(Value::String(message)) => message,
+            Some(message) => message.to_string(),
+            None => format!("SSO request failed with status {}", resp.status),
+        };
+        return Err(SFError::ServerError(message));
     }
     let data = match resp.data {
         Some(data) => data,
         None => match resp.message {
             Some(message) => message,
-            None => return Err(SFError::ConnectionError),
+            None => {
+                return Err(SFError::connection(ConnectionErrorKind::Decode))
+            }
         },
     };
 
@@ -345,11 +355,9 @@ impl ServerLookup {
         let res = client
             .get("https://sfgame.net/config.json")
             .send()
-            .await
-            .map_err(|_| SFError::ConnectionError)?
+            .await?
             .text()
-            .await
-            .map_err(|_| SFError::ConnectionError)?;
+            .await?;
 
         #[derive(Debug, Deserialize, Serialize)]
         struct ServerResp {
@@ -371,7 +379,7 @@ impl ServerLookup {
         }
 
         let resp: ServerResp = serde_json::from_str(&res).map_err(|_| {
-            SFError::ParsingError("server response", res.to_string())
+            SFError::ParsingError("server response", res.clone())
         })?;
 
         let servers: HashMap<i32, Url> = resp
@@ -490,16 +498,16 @@ impl SSOAuth {
         if let Some(message) = val_to_string(&resp) {
             return match message.as_str() {
                 "SSO_POPUP_STATE_PROCESSING" => Ok(AuthResponse::NoAuth(self)),
-                _ => Err(SFError::ConnectionError),
+                _ => Err(SFError::connection(ConnectionErrorKind::Other)),
             };
         }
 
-        let id_token =
-            val_to_string(&resp["id_token"]).ok_or(SFError::ConnectionError)?;
+        let id_token = val_to_string(&resp["id_token"])
+            .ok_or(SFError::connection(ConnectionErrorKind::Decode))?;
 
         let mut form_data = HashMap::new();
         form_data.insert("token".to_string(), id_token.clone());
-        form_data.insert("language".to_string(), "en".to_string());
+        form_data.insert("language".to_string(), self.options.language.clone());
 
         let res = self
             .send_api_request(
@@ -515,11 +523,11 @@ impl SSOAuth {
             .await?;
 
         let access_token = val_to_string(&res["token"]["access_token"])
-            .ok_or(SFError::ConnectionError)?;
+            .ok_or(SFError::connection(ConnectionErrorKind::Decode))?;
         let uuid = val_to_string(&res["account"]["uuid"])
-            .ok_or(SFError::ConnectionError)?;
+            .ok_or(SFError::connection(ConnectionErrorKind::Decode))?;
         let username = val_to_string(&res["account"]["username"])
-            .ok_or(SFError::ConnectionError)?;
+            .ok_or(SFError::connection(ConnectionErrorKind::Decode))?;
 
         Ok(AuthResponse::Success(SFAccount {
             username,
@@ -558,8 +566,8 @@ impl SSOAuth {
         provider: SSOProvider,
         options: ConnectionOptions,
     ) -> Result<Self, SFError> {
-        let client =
-            reqwest_client(&options).ok_or(SFError::ConnectionError)?;
+        let client = reqwest_client(&options)
+            .ok_or(SFError::connection(ConnectionErrorKind::Other))?;
 
         let resp = send_api_request(
             &client,
@@ -571,9 +579,9 @@ impl SSOAuth {
 
         let auth_url = val_to_string(&resp["redirect"])
             .and_then(|a| Url::parse(&a).ok())
-            .ok_or(SFError::ConnectionError)?;
-        let auth_id =
-            val_to_string(&resp["id"]).ok_or(SFError::ConnectionError)?;
+            .ok_or(SFError::connection(ConnectionErrorKind::Decode))?;
+        let auth_id = val_to_string(&resp["id"])
+            .ok_or(SFError::connection(ConnectionErrorKind::Decode))?;
         Ok(Self {
             client,
             options,
@@ -583,3 +591,48 @@ impl SSOAuth {
         })
     }
 }
+
+#[cfg(test)]
+#[allow(clippy::unwrap_used)]
+mod tests {
+    use super::*;
+    use crate::command::Command;
+
+    #[tokio::test]
+    async fn sso_requests_are_authorized() {
+        let account = SFAccount {
+            username: "tester".to_string(),
+            auth: SSOAuthData::Google,
+            session: AccountSession {
+                uuid: "uuid".to_string(),
+                bearer_token: "token".to_string(),
+            },
+            client: Client::new(),
+            options: ConnectionOptions::default(),
+        };
+        let url: Url = "https://s1.sfgame.net/".parse().unwrap();
+        let lookup = ServerLookup(HashMap::from([(1, url.clone())]));
+        let character = SSOCharacter {
+            id: "1".to_string(),
+            name: "DryRun".to_string(),
+            server_id: 1,
+        };
+        let session = Session::from_sso_char(
+            character,
+            Arc::new(Mutex::new(account)),
+            &lookup,
+            &mut HashMap::new(),
+        )
+        .await
+        .unwrap();
+
+        let request = session.prepare(&Command::Update).unwrap();
+        assert!(request
+            .headers
+            .contains(&("referer".to_string(), url.to_string())));
+        assert!(request.headers.contains(&(
+            AUTHORIZATION.to_string(),
+            "Bearer token".to_string()
+        )));
+    }
+}
@@ -0,0 +1,129 @@
+//! Makes sure, that malformed input handed through the C ABI is reported as an
+//! error instead of crashing the host application
+
+use std::ffi::CStr;
+
+use sf_api::ffi::*;
+
+fn last_error_message() -> String {
+    let msg = sf_last_error_message();
+    assert!(!msg.is_null());
+    unsafe { CStr::from_ptr(msg) }
+        .to_string_lossy()
+        .into_owned()
+}
+
+#[test]
+fn malformed_response_is_an_error() {
+    sf_last_error_clear();
+    let response = unsafe { sf_response_parse(c"garbage".as_ptr(), 0) };
+    assert!(response.is_null());
+    assert_eq!(sf_last_error_code(), SfErrorCode::ParsingError);
+
+    sf_last_error_clear();
+    let response = unsafe {
+        sf_response_parse(
+            c"timestamp:1700000000&ownplayersave.playerSave:1/2/x&ownplayername.r:"
+                .as_ptr(),
+            0,
+        )
+    };
+    assert!(!response.is_null());
+
+    let gs = unsafe { sf_gamestate_new(response) };
+    assert!(gs.is_null());
+    assert_ne!(sf_last_error_code(), SfErrorCode::Ok);
+    assert_ne!(sf_last_error_code(), SfErrorCode::Panic);
+    assert!(last_error_message().starts_with("sf_gamestate_new"));
+
+    unsafe { sf_response_free(response) };
+}
+
+#[test]
+fn panic_does_not_unwind_into_c() {
+    let runtime = sf_runtime_new(1);
+    assert!(!runtime.is_null());
+    let session = unsafe {
+        sf_session_new(
+            c"user".as_ptr(),
+            c"pw".as_ptr(),
+            c"s1.sfgame.net".as_ptr(),
+        )
+    };
+    assert!(!session.is_null());
+
+    // Blocking on the runtime from within a runtime thread panics in tokio
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .build()
+        .unwrap();
+    let logged_in =
+        rt.block_on(async { unsafe { sf_session_login(runtime, session) } });
+    assert!(!logged_in);
+    assert_eq!(sf_last_error_code(), SfErrorCode::Panic);
+    assert!(last_error_message().starts_with("sf_session_login"));
+
+    unsafe {
+        sf_session_free(session);
+        sf_runtime_free(runtime);
+    }
+}
+
+#[test]
+fn invalid_command_keeps_its_message() {
+    let session = unsafe {
+        sf_session_new(
+            c"user".as_ptr(),
+            c"pw".as_ptr(),
+            c"s1.sfgame.net".as_ptr(),
+        )
+    };
+    assert!(!session.is_null());
+
+    sf_last_error_clear();
+    let request = unsafe {
+        sf_session_prepare(session, c"{\"NoSuchCommand\":1}".as_ptr())
+    };
+    assert!(request.is_null());
+    assert_eq!(sf_last_error_code(), SfErrorCode::InvalidArgument);
+    assert!(last_error_message()
+        .starts_with("sf_session_prepare: Invalid command:"));
+
+    unsafe { sf_session_free(session) };
+}
+
+#[test]
+fn invalid_enum_values_are_rejected() {
+    assert_eq!(SfDungeonType::try_from(1), Ok(SfDungeonType::Shadow));
+    assert_eq!(SfDungeonType::try_from(2), Err(2));
+    assert_eq!(
+        SfFortressResource::try_from(2),
+        Ok(SfFortressResource::Experience)
+    );
+    assert_eq!(SfFortressResource::try_from(3), Err(3));
+    assert_eq!(SfUnderworldResource::try_from(u32::MAX), Err(u32::MAX));
+
+    let mut resource = SfResource::default();
+    sf_last_error_clear();
+    let ok =
+        unsafe { sf_fortress_resource(std::ptr::null(), 0, &raw mut resource) };
+    assert!(!ok);
+    assert_eq!(sf_last_error_code(), SfErrorCode::InvalidArgument);
+
+    sf_last_error_clear();
+    let ok = unsafe {
+        sf_underworld_resource(std::ptr::null(), 77, &raw mut resource)
+    };
+    assert!(!ok);
+    assert_eq!(sf_last_error_code(), SfErrorCode::InvalidArgument);
+
+    sf_last_error_clear();
+    let progress = unsafe { sf_dungeon_progress(std::ptr::null(), 2, 1) };
+    assert_eq!(progress.state, SfDungeonState::Invalid);
+    assert_eq!(sf_last_error_code(), SfErrorCode::InvalidArgument);
+
+    let mut quest = SfQuest::default();
+    sf_last_error_clear();
+    let ok = unsafe { sf_tavern_quest(std::ptr::null(), 0, &raw mut quest) };
+    assert!(!ok);
+    assert!(last_error_message().starts_with("sf_tavern_quest"));
+}
@@ -0,0 +1,521 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+
+use chrono::Local;
+use sf_api::{
+    command::Command,
+    error::SFError,
+    misc::DEFAULT_CRYPTO_ID,
+    mock_server::{MockFailure, MockServer, MockServerConfig},
+    session::{
+        AccountPool, ConnectionOptions, DryRunTransport, PWHash,
+        PreparedRequest, ProtocolOptions, RetryPolicy, SessionState,
+        SharedSession, SimpleSession, Transport, TransportFuture, YieldPolicy,
+        DRY_RUN_RESPONSE,
+    },
+    ServerConnection, Session,
+};
+
+fn test_options() -> ConnectionOptions {
+    ConnectionOptions {
+        retry_policy: RetryPolicy {
+            initial_backoff: Duration::ZERO,
+            ..RetryPolicy::new(1)
+        },
+        ..Default::default()
+    }
+}
+
+/// Starts a mock server, that accepts any login and responds with a valid
+/// character
+async fn spawn_character_server() -> (MockServer, String) {
+    let server = MockServer::new(
+        MockServerConfig::default()
+            .with_response("AccountLogin", &DRY_RUN_RESPONSE),
+    );
+    let addr = server.spawn("127.0.0.1:0").await.unwrap();
+    (server, format!("http://{addr}/"))
+}
+
+#[tokio::test]
+async fn session_against_mock_server() {
+    let server = MockServer::new(
+        MockServerConfig::default()
+            .with_account("tester", "hunter2")
+            .with_response("PlayerAdventureStart", "Success:"),
+    );
+    let addr = server.spawn("127.0.0.1:0").await.unwrap();
+    let connection = ServerConnection::new_with_options(
+        &format!("http://{addr}/"),
+        test_options(),
+    )
+    .unwrap();
+
+    let mut wrong = Session::new("tester", "wrong", connection.clone());
+    assert!(matches!(wrong.login().await, Err(SFError::ServerError(_))));
+
+    let mut session = Session::new("tester", "hunter2", connection.clone());
+    session.login().await.unwrap();
+    assert!(session.has_session_id());
+    session.send_command(Command::Update).await.unwrap();
+    session
+        .send_command(Command::StartQuest {
+            quest_pos: 0,
+            overwrite_inv: false,
+        })
+        .await
+        .unwrap();
+
+    // The session gets logged in again and the poll is retried
+    server.fail_next(MockFailure::SessionExpired);
+    session.send_command(Command::Update).await.unwrap();
+    assert_eq!(server.session_count(), 1);
+
+    // Starting a quest is not idempotent, so it is not resent, but the
+    // session is logged in again, so that the next command can just work
+    let quest = Command::StartQuest {
+        quest_pos: 0,
+        overwrite_inv: false,
+    };
+    server.fail_next(MockFailure::SessionExpired);
+    let res = session.send_command(&quest).await;
+    assert!(matches!(res, Err(SFError::ServerError(_))));
+    assert_eq!(server.session_count(), 1);
+    session.send_command(&quest).await.unwrap();
+
+    // Ingame errors are never retried
+    server.fail_next(MockFailure::Error("need more silver".into()));
+    let res = session.send_command(Command::Update).await;
+    assert!(matches!(res, Err(SFError::ServerError(_))));
+
+    // Without a takeover window, another login looks just like an expired
+    // session, so both sessions simply log in again
+    let mut other = Session::new("tester", "hunter2", connection.clone());
+    other.login().await.unwrap();
+    session.send_command(Command::Update).await.unwrap();
+    other.send_command(Command::Update).await.unwrap();
+    assert_eq!(server.session_count(), 1);
+    session.login().await.unwrap();
+
+    server.fail_next(MockFailure::Status(502));
+    let res = session.send_command_raw(Command::Update).await;
+    assert!(matches!(
+        res,
+        Err(SFError::ConnectionError {
+            status: Some(502),
+            ..
+        })
+    ));
+}
+
+#[tokio::test]
+async fn pool_limits_concurrent_characters() {
+    let (server, url) = spawn_character_server().await;
+    let mut pool = AccountPool::with_options(2, test_options());
+    let ids: Vec<_> = (0..5)
+        .map(|idx| {
+            pool.add_character(&format!("char{idx}"), "pw", &url)
+                .unwrap()
+        })
+        .collect();
+
+    let active = Arc::new(AtomicUsize::new(0));
+    let max_active = Arc::new(AtomicUsize::new(0));
+    let steps = Arc::new(AtomicUsize::new(0));
+    {
+        let (active, max_active, steps) =
+            (active.clone(), max_active.clone(), steps.clone());
+        pool.run(move |_, mut guard| {
+            let (active, max_active, steps) =
+                (active.clone(), max_active.clone(), steps.clone());
+            async move {
+                let now = active.fetch_add(1, Ordering::SeqCst) + 1;
+                max_active.fetch_max(now, Ordering::SeqCst);
+                let res = guard.send_command(Command::Update).await.map(|_| ());
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                active.fetch_sub(1, Ordering::SeqCst);
+                res?;
+                // The characters act ten times in total
+                let done = steps.fetch_add(1, Ordering::SeqCst) >= 5;
+                Ok((!done).then(Local::now))
+            }
+        })
+        .await;
+    }
+    assert_eq!(max_active.load(Ordering::SeqCst), 2);
+    assert_eq!(active.load(Ordering::SeqCst), 0);
+    assert_eq!(server.session_count(), 5);
+
+    for (idx, id) in ids.into_iter().enumerate() {
+        let status = pool.status(id).unwrap();
+        assert_eq!(status.username, format!("char{idx}"));
+        assert!(status.logged_in);
+        assert_eq!(status.last_error, None);
+        assert_eq!(status.next_wakeup, None);
+    }
+}
+
+#[tokio::test]
+async fn pool_tracks_character_status() {
+    let (server, url) = spawn_character_server().await;
+    let mut pool = AccountPool::with_options(1, test_options());
+    let id = pool.add_character("tester", "pw", &url).unwrap();
+    let status = pool.status(id).unwrap();
+    assert!(!status.logged_in);
+    assert_eq!(status.last_error, None);
+
+    let mut guard = pool.lock(id).await.unwrap();
+    guard.send_command(Command::Update).await.unwrap();
+    assert!(pool.status(id).unwrap().logged_in);
+
+    server.fail_next(MockFailure::Error("need more silver".into()));
+    assert!(guard.send_command(Command::Update).await.is_err());
+    let status = pool.status(id).unwrap();
+    assert!(!status.logged_in);
+    assert!(status.last_error.unwrap().contains("need more silver"));
+
+    guard.send_command(Command::Update).await.unwrap();
+    let status = pool.status(id).unwrap();
+    assert!(status.logged_in);
+    assert_eq!(status.last_error, None);
+    drop(guard);
+
+    // Custom transports are kept, even if the pool already has a connection
+    // to the server
+    let transport = Arc::new(DryRunTransport::default());
+    let connection =
+        ServerConnection::new_with_options(&url, test_options()).unwrap();
+    let mut session = Session::new("dry", "pw", connection);
+    session.set_transport(transport.clone());
+    let dry = pool.add_session(session);
+    pool.lock(dry)
+        .await
+        .unwrap()
+        .send_command(Command::Update)
+        .await
+        .unwrap();
+    assert_eq!(transport.requests().len(), 2);
+    assert_eq!(server.session_count(), 1);
+}
+
+#[tokio::test]
+async fn taken_over_sessions_yield() {
+    let (server, url) = spawn_character_server().await;
+    let options = ConnectionOptions {
+        takeover_window: Some(Duration::from_secs(600)),
+        ..test_options()
+    };
+    let connection = ServerConnection::new_with_options(&url, options).unwrap();
+
+    let mut session = Session::new("tester", "pw", connection.clone());
+    // A session, that has never received anything, can not be taken over
+    server.fail_next(MockFailure::SessionExpired);
+    session.login().await.unwrap();
+    session.send_command(Command::Update).await.unwrap();
+
+    // Logging in again would kick out the human, so it is never retried
+    let mut other = Session::new("tester", "pw", connection.clone());
+    other.login().await.unwrap();
+    let res = session.send_command(Command::Update).await;
+    assert!(matches!(res, Err(SFError::SessionTakenOver)));
+    other.send_command(Command::Update).await.unwrap();
+
+    let taken_over = Arc::new(AtomicUsize::new(0));
+    let mut simple = SimpleSession::new(session, None);
+    simple.send_command(Command::Update).await.unwrap();
+    let counter = taken_over.clone();
+    simple.set_yield_policy(Some(
+        YieldPolicy::new(Duration::from_millis(300)).on_taken_over(move |_| {
+            counter.fetch_add(1, Ordering::SeqCst);
+        }),
+    ));
+
+    other.login().await.unwrap();
+    let res = simple.send_command(Command::Update).await;
+    assert!(matches!(res, Err(SFError::SessionTakenOver)));
+    assert_eq!(taken_over.load(Ordering::SeqCst), 1);
+    let paused_until = simple.paused_until().unwrap();
+    assert!(paused_until > Local::now());
+
+    // During the pause, nothing is sent, so the other login stays valid
+    let res = simple.send_command(Command::Update).await;
+    assert!(matches!(res, Err(SFError::SessionTakenOver)));
+    other.send_command(Command::Update).await.unwrap();
+
+    tokio::time::sleep((paused_until - Local::now()).to_std().unwrap()).await;
+    simple.send_command(Command::Update).await.unwrap();
+    assert_eq!(simple.paused_until(), None);
+    assert_eq!(taken_over.load(Ordering::SeqCst), 1);
+    assert_eq!(server.session_count(), 1);
+}
+
+#[cfg(feature = "cassette")]
+#[tokio::test]
+async fn cassette_round_trip() {
+    use sf_api::session::{RecordingTransport, ReplayTransport};
+
+    let server = MockServer::new(
+        MockServerConfig::default()
+            .with_response("AccountLogin", &DRY_RUN_RESPONSE)
+            .with_response("PlayerAdventureStart", "Success:"),
+    );
+    let path = std::env::temp_dir()
+        .join(format!("sf-api-cassette-{}.jsonl", std::process::id()));
+    let commands = [
+        Command::Update,
+        Command::StartQuest {
+            quest_pos: 0,
+            overwrite_inv: false,
+        },
+    ];
+
+    let recorder =
+        RecordingTransport::create(Arc::new(server.clone()), &path).unwrap();
+    let connection = ServerConnection::new_with_transport(
+        "s1.sfgame.net",
+        test_options(),
+        Arc::new(recorder),
+    )
+    .unwrap();
+    let mut session = Session::new("tester", "pw", connection);
+    let mut recorded = vec![session.login().await.unwrap()];
+    for command in &commands {
+        recorded.push(session.send_command(command).await.unwrap());
+    }
+    drop(session);
+
+    // The replay has to give the same responses without the server
+    let replay = Arc::new(ReplayTransport::open(&path).unwrap());
+    std::fs::remove_file(&path).unwrap();
+    assert_eq!(replay.remaining(), 3);
+    let connection = ServerConnection::new_with_transport(
+        "s1.sfgame.net",
+        test_options(),
+        replay.clone(),
+    )
+    .unwrap();
+    let mut session = Session::new("tester", "pw", connection);
+    let mut replayed = vec![session.login().await.unwrap()];
+    for command in &commands {
+        replayed.push(session.send_command(command).await.unwrap());
+    }
+    assert_eq!(replay.remaining(), 0);
+    for (recorded, replayed) in recorded.iter().zip(&replayed) {
+        assert_eq!(recorded.raw_response(), replayed.raw_response());
+        assert_eq!(recorded.received_at(), replayed.received_at());
+    }
+    assert!(session.has_session_id());
+
+    let res = session.send_command(Command::Update).await;
+    assert!(matches!(res, Err(SFError::InvalidRequest(_))));
+}
+
+#[cfg(feature = "serde")]
+#[tokio::test]
+async fn snapshot_resumes_sessions() {
+    use sf_api::session::Resumed;
+
+    let (server, url) = spawn_character_server().await;
+    let options = ConnectionOptions {
+        takeover_window: Some(Duration::from_secs(600)),
+        ..test_options()
+    };
+    let connection = ServerConnection::new_with_options(&url, options).unwrap();
+    let mut session = Session::new("tester", "pw", connection.clone());
+    session.login().await.unwrap();
+    session.send_command(Command::Update).await.unwrap();
+    let snapshot = session.snapshot().unwrap();
+    drop(session);
+
+    // The server still knows the session, so no login is necessary
+    let (mut session, resumed) =
+        Session::resume(snapshot.clone(), connection.clone())
+            .await
+            .unwrap();
+    assert!(matches!(resumed, Resumed::Continued(_)));
+    session.send_command(Command::Update).await.unwrap();
+    assert_eq!(server.session_count(), 1);
+
+    // Without a takeover window, every rejected session is assumed to have
+    // expired, so the session logs in again
+    let snapshot = session.snapshot().unwrap();
+    server.expire_sessions();
+    let plain =
+        ServerConnection::new_with_options(&url, test_options()).unwrap();
+    let (mut session, resumed) =
+        Session::resume(snapshot, plain).await.unwrap();
+    assert!(matches!(resumed, Resumed::LoggedIn(_)));
+    assert!(resumed.response().values().contains_key("ownplayername"));
+    session.send_command(Command::Update).await.unwrap();
+    assert_eq!(server.session_count(), 1);
+
+    // Somebody else logs in after the snapshot was taken. Resuming must not
+    // kick them out
+    let snapshot = session.snapshot().unwrap();
+    let mut other = Session::new("tester", "pw", connection.clone());
+    other.login().await.unwrap();
+    let res = Session::resume(snapshot, connection).await;
+    assert!(matches!(res, Err(SFError::SessionTakenOver)));
+    other.send_command(Command::Update).await.unwrap();
+}
+
+/// Remembers the order, in which requests arrive at the server
+#[derive(Debug)]
+struct OrderedServer {
+    server: MockServer,
+    received: Mutex<Vec<(u32, String)>>,
+}
+
+impl Transport for OrderedServer {
+    fn send<'a>(&'a self, request: &'a PreparedRequest) -> TransportFuture<'a> {
+        self.received
+            .lock()
+            .unwrap()
+            .push((request.sequence, request.command.clone()));
+        self.server.send(request)
+    }
+}
+
+#[tokio::test]
+async fn shared_session_keeps_the_order_of_commands() {
+    let transport = Arc::new(OrderedServer {
+        server: MockServer::new(
+            MockServerConfig::default()
+                .with_response("PlayerLookAt", "Success:"),
+        ),
+        received: Mutex::default(),
+    });
+    let connection = ServerConnection::new_with_transport(
+        "s1.sfgame.net",
+        test_options(),
+        transport.clone(),
+    )
+    .unwrap();
+    let shared = SharedSession::new(Session::new("tester", "pw", connection));
+    shared.login().await.unwrap();
+
+    // All commands are queued up, while the session is locked
+    let guard = shared.lock().await;
+    let mut tasks = Vec::new();
+    for idx in 0..10 {
+        let shared = shared.clone();
+        tasks.push(tokio::spawn(async move {
+            shared
+                .send_command(Command::ViewPlayer {
+                    ident: idx.to_string(),
+                })
+                .await
+        }));
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    drop(guard);
+    for task in tasks {
+        task.await.unwrap().unwrap();
+    }
+
+    let received = transport.received.lock().unwrap().clone();
+    assert_eq!(received.len(), 11);
+    for (idx, (sequence, command)) in received.iter().enumerate().skip(1) {
+        assert_eq!(*sequence, received[0].0 + idx as u32);
+        assert_eq!(*command, format!("PlayerLookAt:{}", idx - 1));
+    }
+    let session = shared.try_into_inner().unwrap();
+    assert!(session.has_session_id());
+}
+
+/// Hands the request to the mock server and returns the body of its response
+fn exchange(server: &MockServer, request: &PreparedRequest) -> String {
+    let path = format!(
+        "{}?{}",
+        request.url.path(),
+        request.url.query().unwrap_or_default()
+    );
+    let (status, body) = server.handle(&path);
+    assert_eq!(status, 200);
+    body
+}
+
+/// The crypto id, that the request was encrypted for
+fn crypto_id(request: &PreparedRequest) -> String {
+    let (_, req) = request.url.query_pairs().find(|a| a.0 == "req").unwrap();
+    req[..DEFAULT_CRYPTO_ID.len()].to_string()
+}
+
+#[test]
+#[allow(deprecated)]
+fn io_free_round_trip() {
+    let server = MockServer::new(
+        MockServerConfig::default().with_account("tester", "hunter2"),
+    );
+    let url = "https://s1.mock.test/";
+    let mut state =
+        SessionState::new(url.parse().unwrap(), ProtocolOptions::default());
+    let login = Command::Login {
+        username: "tester".into(),
+        pw_hash: PWHash::new("hunter2").get().into(),
+        login_count: state.login_count(),
+    };
+
+    let request = state.prepare(&login).unwrap();
+    assert_eq!(request.sequence, 0);
+    assert_eq!(crypto_id(&request), DEFAULT_CRYPTO_ID);
+    assert!(request
+        .headers
+        .contains(&("referer".to_string(), url.to_string())));
+    let body = exchange(&server, &request);
+
+    // Parsing alone does not log the state in, accepting the response does
+    state
+        .parse(body.clone(), Local::now().naive_local())
+        .unwrap();
+    assert!(!state.has_session_id());
+    state.accept(body, Local::now().naive_local()).unwrap();
+    assert!(state.has_session_id());
+
+    // The next request uses the new crypto id, session id and crypto key.
+    // Otherwise the server would not know the session
+    let request = state.prepare(&Command::Update).unwrap();
+    assert_eq!(request.sequence, 1);
+    assert_ne!(crypto_id(&request), DEFAULT_CRYPTO_ID);
+    let body = exchange(&server, &request);
+    state.accept(body, Local::now().naive_local()).unwrap();
+    assert_eq!(state.prepare(&Command::Update).unwrap().sequence, 2);
+
+    // A session works the same way, without ever touching its transport
+    let connection = ServerConnection::new_with_transport(
+        url,
+        test_options(),
+        Arc::new(DryRunTransport::default()),
+    )
+    .unwrap();
+    let mut session = Session::new("tester", "hunter2", connection);
+    let login = Command::Login {
+        username: "tester".into(),
+        pw_hash: PWHash::new("hunter2").get().into(),
+        login_count: session.state().login_count(),
+    };
+    let request = session.prepare(&login).unwrap();
+    let body = exchange(&server, &request);
+    session.accept(body, Local::now().naive_local()).unwrap();
+    assert!(session.has_session_id());
+    let request = session.prepare(&Command::Update).unwrap();
+    assert_eq!(request.sequence, 1);
+    assert!(request
+        .headers
+        .contains(&("referer".to_string(), url.to_string())));
+    let body = exchange(&server, &request);
+    session.accept(body, Local::now().naive_local()).unwrap();
+
+    // Only the latest login is valid, so the first state was kicked out
+    let request = state.prepare(&Command::Update).unwrap();
+    let body = exchange(&server, &request);
+    assert!(matches!(
+        state.accept(body, Local::now().naive_local()),
+        Err(SFError::ServerError(_))
+    ));
+}
@@ -0,0 +1,519 @@
+//! Checks the policies, that decide how sessions react to errors and how fast
+//! they send requests, without talking to any server
+
+use std::{
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use sf_api::{
+    command::*,
+    error::{ConnectionErrorKind, ErrorClass, SFError},
+    gamestate::{
+        character::{Class, Gender, Mount, Race},
+        dungeons::LightDungeon,
+        fortress::FortressBuildingType,
+        items::{ItemPlace, PlayerItemPlace},
+        underworld::UnderworldBuildingType,
+        unlockables::{HabitatType, HellevatorTreatType},
+        GameState,
+    },
+    response::Response,
+    session::{
+        ConnectionOptions, DryRunTransport, Interceptor, MushroomGuard,
+        RateLimit, RetryPolicy, SimpleSession, Verdict,
+    },
+    ServerConnection, Session,
+};
+
+#[test]
+fn errors_are_classified() {
+    let class = |err: SFError| err.class();
+    assert_eq!(
+        class(SFError::connection(ConnectionErrorKind::Timeout)),
+        ErrorClass::Connection
+    );
+    assert_eq!(class(SFError::http_status(502)), ErrorClass::Connection);
+    assert_eq!(class(SFError::EmptyResponse), ErrorClass::Session);
+    assert_eq!(
+        class(SFError::ServerError("cryptoid not found".into())),
+        ErrorClass::Session
+    );
+    assert_eq!(
+        class(SFError::ServerError("need more silver".into())),
+        ErrorClass::Game
+    );
+    assert_eq!(
+        class(SFError::ParsingError("test", String::new())),
+        ErrorClass::Game
+    );
+    assert_eq!(
+        class(SFError::CommandDenied("test".into())),
+        ErrorClass::Game
+    );
+    assert_eq!(class(SFError::SessionTakenOver), ErrorClass::TakenOver);
+}
+
+#[test]
+fn backoff_grows_until_the_limit() {
+    let policy = RetryPolicy {
+        initial_backoff: Duration::from_millis(100),
+        backoff_factor: 2.0,
+        max_backoff: Duration::from_secs(1),
+        ..RetryPolicy::new(10)
+    };
+    assert_eq!(policy.backoff(1), Duration::from_millis(100));
+    assert_eq!(policy.backoff(2), Duration::from_millis(200));
+    assert_eq!(policy.backoff(4), Duration::from_millis(800));
+    assert_eq!(policy.backoff(5), Duration::from_secs(1));
+    assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(1));
+
+    // Factors below 1 would shrink the backoff
+    let policy = RetryPolicy {
+        backoff_factor: 0.5,
+        ..policy
+    };
+    assert_eq!(policy.backoff(3), Duration::from_millis(100));
+}
+
+#[test]
+fn jitter_stays_within_its_share() {
+    let policy = RetryPolicy {
+        initial_backoff: Duration::from_millis(1000),
+        jitter: 0.25,
+        ..RetryPolicy::new(3)
+    };
+    for _ in 0..1000 {
+        let backoff = policy.jittered_backoff(1);
+        assert!(backoff >= Duration::from_millis(750), "{backoff:?}");
+        assert!(backoff <= Duration::from_millis(1250), "{backoff:?}");
+    }
+
+    for jitter in [0.0, -1.0, f64::NAN] {
+        let policy = RetryPolicy { jitter, ..policy };
+        assert_eq!(policy.jittered_backoff(1), policy.backoff(1));
+    }
+    let policy = RetryPolicy {
+        jitter: 5.0,
+        ..policy
+    };
+    assert!(policy.jittered_backoff(1) <= Duration::from_millis(2000));
+}
+
+#[test]
+fn mushroom_guard_denies_every_mushroom_command() {
+    let fight = |use_mushroom| Command::Fight {
+        name: "test".into(),
+        use_mushroom,
+    };
+    let dungeon = |use_mushroom| Command::FightDungeon {
+        dungeon: LightDungeon::MinesOfGloria.into(),
+        use_mushroom,
+    };
+    let tower = |use_mush| Command::FightTower {
+        current_level: 1,
+        use_mush,
+    };
+    let pet_dungeon = |use_mush| Command::FightPetDungeon {
+        use_mush,
+        habitat: HabitatType::Shadow,
+        enemy_pos: 1,
+        player_pet_id: 1,
+    };
+    let hellevator_buy = |use_mushroom| Command::HellevatorBuy {
+        position: 0,
+        typ: HellevatorTreatType::Electroshock,
+        price: 1,
+        use_mushroom,
+    };
+    let hell_attack = |use_mushroom| Command::HellevatorJoinHellAttack {
+        use_mushroom,
+        plain: 0,
+    };
+    let finish_quest = |skip| Command::FinishQuest { skip };
+    let expedition_skip = |typ| Command::ExpeditionSkipWait { typ };
+    let build_finish = |mushrooms| Command::FortressBuildFinish {
+        f_type: FortressBuildingType::Fortress,
+        mushrooms,
+    };
+    let gem_finish =
+        |mushrooms| Command::FortressGemStoneSearchFinish { mushrooms };
+    let underworld_start = |mushrooms| Command::UnderworldUpgradeStart {
+        building: UnderworldBuildingType::Gate,
+        mushrooms,
+    };
+    let underworld_finish = |mushrooms| Command::UnderworldUpgradeFinish {
+        building: UnderworldBuildingType::Gate,
+        mushrooms,
+    };
+    let blacksmith = |action| Command::Blacksmith {
+        inventory_t: PlayerItemPlace::MainInventory,
+        position: 0,
+        action,
+    };
+    let roll_dice = |payment| Command::RollDice {
+        payment,
+        dices: [DiceType::ReRoll; 5],
+    };
+
+    let paid = [
+        fight(true),
+        dungeon(true),
+        tower(true),
+        pet_dungeon(true),
+        Command::FortressNewEnemy { use_mushroom: true },
+        Command::GuildPetBattle { use_mushroom: true },
+        Command::HellevatorFight { use_mushroom: true },
+        hellevator_buy(true),
+        hell_attack(true),
+        finish_quest(Some(TimeSkip::Mushroom)),
+        expedition_skip(TimeSkip::Mushroom),
+        build_finish(1),
+        gem_finish(3),
+        underworld_start(2),
+        underworld_finish(2),
+        Command::BuyMount {
+            mount: Mount::Tiger,
+        },
+        Command::BuyMount {
+            mount: Mount::Dragon,
+        },
+        Command::SpinWheelOfFortune {
+            payment: FortunePayment::Mushrooms,
+        },
+        roll_dice(RollDicePrice::Mushrooms),
+        blacksmith(BlacksmithAction::SocketUpgradeWithMushrooms),
+        blacksmith(BlacksmithAction::GemExtractWithMushrooms),
+        Command::GambleMushrooms { amount: 1 },
+        Command::BuyBeer,
+        Command::GuildLoadMushrooms,
+        Command::RefreshShop {
+            shop: ShopType::Magic,
+        },
+        Command::ChangeItemLook {
+            inv: ItemPlace::Equipment,
+            pos: 0,
+            raw_model_id: 1,
+        },
+        Command::BuyGoldFrame,
+    ];
+    let free = [
+        fight(false),
+        dungeon(false),
+        tower(false),
+        pet_dungeon(false),
+        Command::FortressNewEnemy {
+            use_mushroom: false,
+        },
+        Command::GuildPetBattle {
+            use_mushroom: false,
+        },
+        Command::HellevatorFight {
+            use_mushroom: false,
+        },
+        hellevator_buy(false),
+        hell_attack(false),
+        finish_quest(None),
+        finish_quest(Some(TimeSkip::Glass)),
+        expedition_skip(TimeSkip::Glass),
+        build_finish(0),
+        gem_finish(0),
+        underworld_start(0),
+        underworld_finish(0),
+        Command::BuyMount { mount: Mount::Cow },
+        Command::SpinWheelOfFortune {
+            payment: FortunePayment::FreeTurn,
+        },
+        roll_dice(RollDicePrice::Free),
+        blacksmith(BlacksmithAction::SocketUpgrade),
+        Command::Update,
+    ];
+
+    let guard = MushroomGuard;
+    for command in paid {
+        assert!(
+            matches!(guard.before_send(&command), Verdict::Deny(_)),
+            "{command:?} was allowed"
+        );
+    }
+    for command in free {
+        assert_eq!(guard.before_send(&command), Verdict::Allow);
+    }
+}
+
+#[tokio::test]
+async fn dry_run_captures_requests() {
+    let transport = Arc::new(DryRunTransport::default());
+    let connection = ServerConnection::new_with_transport(
+        "s1.sfgame.net",
+        ConnectionOptions::default(),
+        transport.clone(),
+    )
+    .unwrap();
+    let mut session = Session::new("tester", "hunter2", connection);
+
+    let resp = session.login().await.unwrap();
+    let mut gs = GameState::new(resp).unwrap();
+    assert_eq!(gs.character.name, "DryRun");
+    assert_eq!(gs.character.level, 1);
+
+    let resp = session.send_command(Command::Update).await.unwrap();
+    gs.update(resp).unwrap();
+    session
+        .send_command(Command::StartQuest {
+            quest_pos: 1,
+            overwrite_inv: false,
+        })
+        .await
+        .unwrap();
+
+    let requests = transport.take_requests();
+    let commands: Vec<_> =
+        requests.iter().map(|a| a.command.as_str()).collect();
+    assert_eq!(commands.len(), 3);
+    assert!(commands[0].starts_with("AccountLogin:tester/"));
+    assert_eq!(commands[1], "Poll:");
+    assert_eq!(commands[2], "PlayerAdventureStart:2/0");
+    assert!(requests
+        .iter()
+        .all(|a| a.url.host_str() == Some("s1.sfgame.net")));
+    assert_eq!(requests[2].sequence, requests[1].sequence + 1);
+    assert!(transport.requests().is_empty());
+}
+
+#[test]
+#[allow(deprecated)]
+fn redacted_commands_hide_credentials() {
+    let commands = [
+        Command::Login {
+            username: "tester".into(),
+            pw_hash: "secret".into(),
+            login_count: 1,
+        },
+        Command::Register {
+            username: "tester".into(),
+            password: "secret".into(),
+            gender: sf_api::gamestate::character::Gender::Female,
+            race: sf_api::gamestate::character::Race::Elf,
+            class: sf_api::gamestate::character::Class::Mage,
+        },
+        Command::ChangePassword {
+            username: "tester".into(),
+            old: "secret".into(),
+            new: "secret".into(),
+        },
+        Command::ChangeMailAddress {
+            old_mail: "old@mail".into(),
+            new_mail: "new@mail".into(),
+            password: "secret".into(),
+            username: "tester".into(),
+        },
+    ];
+    for command in commands {
+        let redacted = format!("{:?}", command.redacted());
+        assert!(!redacted.contains("secret"), "{redacted}");
+        assert!(redacted.contains("tester"), "{redacted}");
+    }
+    assert_eq!(
+        format!("{:?}", Command::Update.redacted()),
+        format!("{:?}", Command::Update)
+    );
+}
+
+/// Writes down every command it sees, together with its own name
+#[derive(Debug)]
+struct Recorder {
+    name: &'static str,
+    log: Arc<Mutex<Vec<String>>>,
+}
+
+impl Interceptor for Recorder {
+    fn before_send(&self, command: &Command) -> Verdict {
+        let entry = format!("{} before {command:?}", self.name);
+        self.log.lock().unwrap().push(entry);
+        Verdict::Allow
+    }
+
+    fn after_receive(
+        &self,
+        command: &Command,
+        result: &Result<Response, SFError>,
+    ) {
+        let entry =
+            format!("{} after {command:?} ok: {}", self.name, result.is_ok());
+        self.log.lock().unwrap().push(entry);
+    }
+}
+
+/// Looks at a different player, than the one requested
+#[derive(Debug)]
+struct Rewriter;
+
+impl Interceptor for Rewriter {
+    fn before_send(&self, command: &Command) -> Verdict {
+        match command {
+            Command::ViewPlayer { ident } if ident == "a" => {
+                Verdict::Rewrite(Command::ViewPlayer { ident: "b".into() })
+            }
+            _ => Verdict::Allow,
+        }
+    }
+}
+
+#[tokio::test]
+async fn interceptors_run_in_order() {
+    let transport = Arc::new(DryRunTransport::default());
+    let connection = ServerConnection::new_with_transport(
+        "s1.sfgame.net",
+        ConnectionOptions::default(),
+        transport.clone(),
+    )
+    .unwrap();
+    let log = Arc::new(Mutex::new(Vec::new()));
+    let mut session = Session::new("tester", "hunter2", connection);
+    session.add_interceptor(Recorder {
+        name: "first",
+        log: log.clone(),
+    });
+    session.add_interceptor(Rewriter);
+    session.add_interceptor(Recorder {
+        name: "second",
+        log: log.clone(),
+    });
+    session.add_interceptor(MushroomGuard);
+    let mut session = SimpleSession::new(session, None);
+
+    // The login of the session is intercepted as well
+    session
+        .send_command(Command::ViewPlayer { ident: "a".into() })
+        .await
+        .unwrap();
+    let requests = transport.take_requests();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0].command.starts_with("AccountLogin:tester/"));
+    assert_eq!(requests[1].command, "PlayerLookAt:b");
+
+    let a = r#"ViewPlayer { ident: "a" }"#;
+    let b = r#"ViewPlayer { ident: "b" }"#;
+    let log = std::mem::take(&mut *log.lock().unwrap());
+    assert_eq!(log.len(), 8);
+    assert!(log[0].starts_with("first before Login"));
+    assert!(log[1].starts_with("second before Login"));
+    assert_eq!(
+        &log[4..],
+        [
+            format!("first before {a}"),
+            format!("second before {b}"),
+            format!("first after {b} ok: true"),
+            format!("second after {b} ok: true"),
+        ]
+    );
+
+    // Denied commands are never sent and do not throw away the login
+    let res = session.send_command(Command::BuyBeer).await;
+    assert!(matches!(res, Err(SFError::CommandDenied(_))));
+    assert!(transport.take_requests().is_empty());
+    assert!(session.game_state().is_some());
+    session.send_command(Command::Update).await.unwrap();
+    let requests = transport.take_requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(requests[0].command, "Poll:");
+}
+
+#[tokio::test]
+async fn server_limit_is_shared_by_connection_clones() {
+    let interval = Duration::from_millis(300);
+    let options = ConnectionOptions {
+        rate_limit: RateLimit {
+            server_burst: 1,
+            server_interval: interval,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let transport = Arc::new(DryRunTransport::default());
+    let connection = ServerConnection::new_with_transport(
+        "s1.sfgame.net",
+        options.clone(),
+        transport.clone(),
+    )
+    .unwrap();
+    // Same server, but maybe through another proxy
+    let independent = ServerConnection::new_with_transport(
+        "s1.sfgame.net",
+        options,
+        transport.clone(),
+    )
+    .unwrap();
+
+    let a = Session::new("a", "pw", connection.clone());
+    let b = Session::new("b", "pw", connection);
+    let c = Session::new("c", "pw", independent);
+
+    let start = std::time::Instant::now();
+    a.send_command_raw(Command::Update).await.unwrap();
+    c.send_command_raw(Command::Update).await.unwrap();
+    assert!(start.elapsed() < interval);
+    b.send_command_raw(Command::Update).await.unwrap();
+    assert!(start.elapsed() >= interval);
+    assert_eq!(transport.take_requests().len(), 3);
+}
+
+#[tokio::test]
+#[allow(deprecated)]
+async fn dry_run_uses_custom_client_options() {
+    let options = ConnectionOptions {
+        app_version: "2200000000000".to_string(),
+        platform: "android".to_string(),
+        language: "de".to_string(),
+        ..Default::default()
+    };
+    let transport = Arc::new(DryRunTransport::default());
+    let connection = ServerConnection::new_with_transport(
+        "s1.sfgame.net",
+        options,
+        transport.clone(),
+    )
+    .unwrap();
+    let mut session = Session::new("tester", "hunter2", connection);
+    session.login().await.unwrap();
+    session
+        .send_command_raw(Command::Register {
+            username: "tester".to_string(),
+            password: "hunter2".to_string(),
+            gender: Gender::Female,
+            race: Race::Elf,
+            class: Class::Scout,
+        })
+        .await
+        .unwrap();
+    #[cfg(feature = "sso")]
+    session
+        .send_command_raw(Command::SSOLogin {
+            uuid: "uuid".to_string(),
+            character_id: "1".to_string(),
+            bearer_token: "token".to_string(),
+        })
+        .await
+        .unwrap();
+
+    let requests = transport.take_requests();
+    let login = &requests[0].command;
+    assert!(login.starts_with("AccountLogin:tester/"));
+    assert!(login.ends_with("/android//2200000000000///0/"));
+    assert!(requests[1]
+        .command
+        .starts_with("AccountCreate:tester/hunter2/"));
+    assert!(requests[1].command.ends_with("/0//de"));
+    #[cfg(feature = "sso")]
+    assert_eq!(
+        requests[2].command,
+        "SFAccountCharLogin:uuid/1/android//2200000000000"
+    );
+    for request in requests {
+        assert!(request.headers.contains(&(
+            "accept-language".to_string(),
+            "de;q=0.7,en-US;q=0.6".to_string()
+        )));
+    }
+}